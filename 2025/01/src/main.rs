@@ -1,6 +1,7 @@
+use common::parse::line;
 use winnow::{
-    ascii::{dec_uint, line_ending},
-    combinator::{alt, eof, repeat, seq, terminated},
+    ascii::dec_uint,
+    combinator::{alt, repeat, seq},
     prelude::*,
 };
 
@@ -47,7 +48,7 @@ impl Entry {
 }
 
 fn calculate<F: Fn(u64, u64, Entry) -> (u64, u64)>(input: &str, logic: F) -> u64 {
-    repeat(0.., terminated(parse_entry, alt((line_ending, eof))))
+    repeat(0.., line(parse_entry))
         .fold(|| (50, 0), |(dial, zeros), entry| logic(dial, zeros, entry))
         .map(|(_dial, zeros)| zeros)
         .parse(input)
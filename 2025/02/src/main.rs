@@ -111,19 +111,12 @@ fn test_bad_id_block_ctor(
 }
 
 fn parser(input: &mut &str) -> ModalResult<Vec<RangeInclusive<u64>>> {
+    use common::parse::range_inclusive;
     use winnow::{
-        ascii::{dec_uint, line_ending},
-        combinator::{opt, separated, separated_pair, terminated},
+        ascii::line_ending,
+        combinator::{opt, separated, terminated},
     };
-    terminated(
-        separated(
-            1..,
-            separated_pair(dec_uint, '-', dec_uint).map(|(lo, hi)| lo..=hi),
-            ',',
-        ),
-        opt(line_ending),
-    )
-    .parse_next(input)
+    terminated(separated(1.., range_inclusive(), ','), opt(line_ending)).parse_next(input)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
@@ -138,6 +131,10 @@ struct Args {
     input_file: PathBuf,
     #[arg(long)]
     explain: bool,
+    /// number of worker threads to use (defaults to one per cpu)
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    threads: Option<usize>,
 }
 
 fn build_bad_id_blocks(ruleset: Ruleset) -> Vec<BadIdBlock> {
@@ -172,18 +169,47 @@ fn get_bad_ids_in_range(
         .unique()
 }
 
+fn sum_bad_ids(ranges: &[RangeInclusive<u64>], blocks: &[BadIdBlock]) -> u64 {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        ranges
+            .par_iter()
+            .map(|range| get_bad_ids_in_range(range, blocks).sum::<u64>())
+            .sum()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        sum_bad_ids_sequential(ranges, blocks)
+    }
+}
+
+/// [`sum_bad_ids`] a range at a time, as it's worked out without the `parallel` feature
+#[cfg(any(test, not(feature = "parallel")))]
+fn sum_bad_ids_sequential(ranges: &[RangeInclusive<u64>], blocks: &[BadIdBlock]) -> u64 {
+    ranges
+        .iter()
+        .flat_map(|range| get_bad_ids_in_range(range, blocks))
+        .sum()
+}
+
 fn main() {
     let args = Args::parse();
 
+    #[cfg(feature = "parallel")]
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("building thread pool failed");
+    }
+
     let bad_id_blocks = build_bad_id_blocks(args.ruleset);
 
     let txt = std::fs::read_to_string(args.input_file).expect("reading input file failed");
     let product_ranges = parser.parse(&txt).expect("parsing input failed");
 
-    let answer = product_ranges
-        .iter()
-        .flat_map(|input_range| get_bad_ids_in_range(input_range, &bad_id_blocks))
-        .sum::<u64>();
+    let answer = sum_bad_ids(&product_ranges, &bad_id_blocks);
     println!("{answer}");
 
     if args.explain {
@@ -226,3 +252,22 @@ fn test_bad_ids_in_range(
         get_bad_ids_in_range(&range, &build_bad_id_blocks(ruleset)).collect::<HashSet<_>>()
     );
 }
+
+#[cfg(all(test, feature = "parallel"))]
+#[rstest]
+#[case::part1(Ruleset::Part1)]
+#[case::part2(Ruleset::Part2)]
+fn test_parallel_matches_sequential(#[case] ruleset: Ruleset) {
+    let ranges = parser
+        .parse(
+            "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,\
+             446443-446449,38593856-38593862,565653-565659,824824821-824824827,\
+             2121212118-2121212124",
+        )
+        .unwrap();
+    let blocks = build_bad_id_blocks(ruleset);
+    assert_eq!(
+        sum_bad_ids_sequential(&ranges, &blocks),
+        sum_bad_ids(&ranges, &blocks)
+    );
+}
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+
+use clap::Parser as _;
 use itertools::Itertools;
 use tap::{Pipe as _, Tap as _};
 use winnow::{
@@ -7,20 +10,70 @@ use winnow::{
     token::take,
 };
 
+#[derive(clap::Parser)]
+struct Args {
+    input_file: PathBuf,
+    /// number of worker threads to use (defaults to one per cpu)
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
 fn main() {
-    let input_file = std::env::args().nth(1).expect("no file specified");
-    let txt = std::fs::read_to_string(input_file).expect("reading input file failed");
+    let args = Args::parse();
+
+    #[cfg(feature = "parallel")]
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("building thread pool failed");
+    }
+
+    let txt = std::fs::read_to_string(args.input_file).expect("reading input file failed");
 
     let banks = parse_banks
         .parse(txt.as_str())
         .expect("parsing input failed");
 
-    let answer_pt1: u64 = banks.iter().map(|bank| logic(bank, 2)).sum();
+    let answer_pt1 = total_joltage(&banks, 2);
     dbg!(answer_pt1);
-    let answer_pt2: u64 = banks.iter().map(|bank| logic(bank, 12)).sum();
+    let answer_pt2 = total_joltage(&banks, 12);
     dbg!(answer_pt2);
 }
 
+fn total_joltage(banks: &[Vec<u8>], ndigits: usize) -> u64 {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        banks.par_iter().map(|bank| logic(bank, ndigits)).sum()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        total_joltage_sequential(banks, ndigits)
+    }
+}
+
+/// [`total_joltage`] a bank at a time, as it's worked out without the `parallel` feature
+#[cfg(any(test, not(feature = "parallel")))]
+fn total_joltage_sequential(banks: &[Vec<u8>], ndigits: usize) -> u64 {
+    banks.iter().map(|bank| logic(bank, ndigits)).sum()
+}
+
+#[cfg(all(test, feature = "parallel"))]
+#[test]
+fn test_parallel_matches_sequential() {
+    let banks = parse_banks
+        .parse("987654321111111\n811111111111119\n234234234234278\n818181911112111\n")
+        .unwrap();
+    for ndigits in [2, 12] {
+        assert_eq!(
+            total_joltage_sequential(&banks, ndigits),
+            total_joltage(&banks, ndigits)
+        );
+    }
+}
+
 fn logic(bank: &[u8], ndigits: usize) -> u64 {
     let mut ret = 0u64;
 
@@ -1,19 +1,194 @@
-use std::fmt::Display;
+use std::{collections::VecDeque, fmt::Display, io::BufRead as _, path::PathBuf};
 
-use grid::Grid;
+use clap::Parser as _;
+use common::grid::{Boundary, Connectivity, Grid, Pos};
 use itertools::Itertools;
+#[cfg(test)]
+use rstest::rstest;
 use tap::Pipe as _;
-use winnow::Parser as _;
+
+#[derive(clap::Parser)]
+struct Args {
+    #[arg(default_value = "input.txt")]
+    input_file: PathBuf,
+    /// remove rolls in waves, reporting how many were removed by each wave
+    #[arg(long)]
+    waves: bool,
+    /// (with --waves) print the floor after every wave
+    #[arg(long, requires = "waves")]
+    animate: bool,
+    /// (with --waves) write the floor after every wave to numbered files in this directory
+    #[arg(long, requires = "waves")]
+    frames_dir: Option<PathBuf>,
+    /// treat rows shorter than the longest row as being padded with empty cells
+    #[arg(long)]
+    pad: bool,
+    /// shape of the neighbourhood checked around each roll
+    #[arg(long, value_enum, default_value_t = Neighbourhood::Moore)]
+    neighbourhood: Neighbourhood,
+    /// (for moore and von-neumann neighbourhoods) how far the neighbourhood extends. at most 127,
+    /// so that a cell's count of paper neighbours fits in a u16
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(..=127))]
+    radius: u16,
+    /// (for custom neighbourhoods, which need at least one) `row,col` offset to a neighbour. may
+    /// be given multiple times
+    #[arg(
+        long = "offset",
+        allow_hyphen_values = true,
+        value_parser = parse_offset,
+        required_if_eq("neighbourhood", "custom")
+    )]
+    offsets: Vec<(isize, isize)>,
+    /// a roll is accessible if fewer than this many of its neighbours are paper
+    #[arg(long, default_value_t = 4)]
+    threshold: u16,
+    /// what neighbours past the edges of the floor are
+    #[arg(long, value_enum, default_value_t = Edges::Bounded)]
+    edges: Edges,
+    /// how the floor is stored. `cells` keeps a byte and a cached neighbour count per cell, while
+    /// `bits` keeps a single bit, about a 24th of the memory, and reads the input a line at a time
+    /// rather than all at once. `bits` works out which rolls are accessible many cells at a time,
+    /// but is slower to remove rolls from.
+    #[arg(long, value_enum, default_value_t = Backend::Cells)]
+    backend: Backend,
+    /// draw the floor to an image. `.ppm` always works, `.png` needs the `png` feature
+    #[arg(long)]
+    render: Option<PathBuf>,
+    /// (with --render) width and height in pixels of each cell
+    #[arg(long, default_value_t = 4, requires = "render")]
+    scale: usize,
+    /// (with --render and --waves) shade removed rolls by which wave removed them
+    #[arg(long, requires_all = ["render", "waves"])]
+    shade_waves: bool,
+    /// check the floor's incrementally-updated state against a full recomputation after every
+    /// roll is removed. very slow, but catches bugs as soon as they happen
+    #[arg(long)]
+    paranoid: bool,
+}
+
+impl Args {
+    /// the checks between arguments which clap can't express
+    fn validated(self) -> Result<Self, clap::Error> {
+        use clap::CommandFactory as _;
+        if !self.offsets.is_empty() && self.neighbourhood != Neighbourhood::Custom {
+            return Err(Args::command().error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--offset only applies to --neighbourhood custom",
+            ));
+        }
+        if let Some(Err(err)) = self.render.as_deref().map(render::Format::of) {
+            return Err(Args::command().error(
+                clap::error::ErrorKind::ValueValidation,
+                format!("--render: {err}"),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::default(&[], true)]
+#[case::max_radius(&["--radius", "127"], true)]
+#[case::radius_too_big(&["--radius", "128"], false)]
+#[case::custom(&["--neighbourhood", "custom", "--offset", "-1,0"], true)]
+#[case::custom_without_offsets(&["--neighbourhood", "custom"], false)]
+#[case::offset_without_custom(&["--offset", "-1,0"], false)]
+#[case::render_ppm(&["--render", "floor.ppm"], true)]
+#[case::render_unknown_format(&["--render", "floor.txt"], false)]
+fn test_args(#[case] args: &[&str], #[case] valid: bool) {
+    let parsed = Args::try_parse_from(["day04"].iter().chain(args)).and_then(Args::validated);
+    assert_eq!(valid, parsed.is_ok());
+}
+
+fn parse_offset(s: &str) -> Result<(isize, isize), String> {
+    let (dx, dy) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `row,col`, got `{s}`"))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<isize>()
+            .map_err(|err| format!("`{n}`: {err}"))
+    };
+    Ok((parse(dx)?, parse(dy)?))
+}
 
 fn main() {
-    let input_file = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "input.txt".into());
-    let txt = std::fs::read_to_string(input_file).expect("reading input file failed");
+    let args = Args::parse().validated().unwrap_or_else(|err| err.exit());
 
-    let mut g = DeptFloor::parse(txt.as_str()).expect("parsing input failed");
+    let rule = match args.neighbourhood {
+        Neighbourhood::Moore => Rule::moore(args.radius.into(), args.threshold),
+        Neighbourhood::VonNeumann => Rule::von_neumann(args.radius.into(), args.threshold),
+        Neighbourhood::Custom => Rule::custom(args.offsets, args.threshold),
+    }
+    .with_edges(args.edges);
+
+    let mut g = match args.backend {
+        Backend::Cells => {
+            let txt = std::fs::read_to_string(&args.input_file).expect("reading input file failed");
+            parser::grid(txt.as_str(), args.pad).map(|ground| DeptFloor::new(ground, rule))
+        }
+        Backend::Bits => {
+            // a line at a time, so a floor too big to hold as text can still be read
+            let lines = || {
+                std::fs::File::open(&args.input_file)
+                    .map(std::io::BufReader::new)
+                    .expect("reading input file failed")
+                    .lines()
+                    .map(|line| line.expect("reading input file failed"))
+            };
+            let pad_to = args.pad.then(|| parser::max_width(lines()));
+            parser::bitboard(lines(), pad_to).map(|ground| DeptFloor::new_bits(ground, rule))
+        }
+    }
+    .unwrap_or_else(|err| panic!("parsing input failed: {err}"))
+    .with_paranoid(args.paranoid);
     dbg!(g.n_accessible_rolls());
-    dbg!(solve_pt2(&mut g));
+
+    let mut render = args.render.as_ref().map(|_| render::Tiles::new(&g));
+    let save_render = |render: Option<render::Tiles>| {
+        if let (Some(tiles), Some(path)) = (render, &args.render) {
+            tiles
+                .image(args.shade_waves, args.scale)
+                .save(path)
+                .unwrap_or_else(|err| panic!("saving render failed: {err}"));
+        }
+    };
+
+    if !args.waves {
+        dbg!(solve_pt2(&mut g));
+        if let Some(tiles) = &mut render {
+            tiles.record_removed_since_start(&g);
+        }
+        save_render(render);
+        return;
+    }
+
+    if let Some(dir) = &args.frames_dir {
+        std::fs::create_dir_all(dir).expect("creating frames directory failed");
+    }
+    let mut show_frame = |wave: usize, removed: &[Pos], floor: &DeptFloor| {
+        if let Some(tiles) = &mut render {
+            tiles.record_wave(wave, removed);
+        }
+        if args.animate {
+            println!("wave {wave}:\n{floor}\n");
+        }
+        if let Some(dir) = &args.frames_dir {
+            std::fs::write(
+                dir.join(format!("frame_{wave:04}.txt")),
+                format!("{floor}\n"),
+            )
+            .expect("writing frame failed");
+        }
+    };
+    show_frame(0, &[], &g);
+    let wave_sizes = solve_waves(&mut g, show_frame);
+    for (idx, n) in wave_sizes.iter().enumerate() {
+        println!("wave {}: removed {n}", idx + 1);
+    }
+    dbg!(wave_sizes.iter().sum::<usize>());
+    save_render(render);
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -32,124 +207,1178 @@ impl Display for CellKind {
     }
 }
 
-fn surrounding_indices(
-    (x, y): (usize, usize),
-    radius: usize,
-) -> impl Iterator<Item = (usize, usize)> {
-    (x.saturating_sub(radius)..=(x + radius))
-        .cartesian_product(y.saturating_sub(radius)..=(y + radius))
-        .filter(move |&(ax, ay)| ax != x || ay != y)
+/// which cells count as a cell's neighbours, and how few of those can be paper for a roll there to
+/// be accessible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    /// a roll is accessible if fewer than this many of its neighbours are paper
+    threshold: u16,
+    /// `(row, col)` offsets from a cell to each of its neighbours
+    offsets: Vec<(isize, isize)>,
+    edges: Edges,
+}
+
+/// what neighbours which fall past the edges of the floor are
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum Edges {
+    /// there's nothing past the edges
+    #[default]
+    Bounded,
+    /// the floor wraps around, so past each edge is the opposite edge
+    Wrap,
+    /// everything past the edges counts as paper (which can never be removed)
+    Paper,
+}
+
+impl Edges {
+    /// how to find cells past the edges, for the kinds of edge that have cells past them
+    fn boundary(self) -> Boundary {
+        match self {
+            Edges::Wrap => Boundary::Wrapping,
+            Edges::Bounded | Edges::Paper => Boundary::Bounded,
+        }
+    }
+}
+
+/// a neighbour of some cell, as determined by a [`Rule`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Neighbour {
+    Cell(Pos),
+    /// past the edge of the floor, when the edges count as paper
+    Edge,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Neighbourhood {
+    /// every cell within a square of the given radius
+    Moore,
+    /// every cell within the given manhattan distance
+    VonNeumann,
+    /// an explicit list of offsets
+    Custom,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::moore(1, 4)
+    }
+}
+
+impl Rule {
+    fn moore(radius: usize, threshold: u16) -> Self {
+        Self::custom(Connectivity::Eight.offsets(radius), threshold)
+    }
+
+    fn von_neumann(radius: usize, threshold: u16) -> Self {
+        Self::custom(Connectivity::Four.offsets(radius), threshold)
+    }
+
+    /// a cell is never its own neighbour, so `(0, 0)` is dropped from the given offsets, as are
+    /// any duplicates.
+    fn custom(offsets: impl IntoIterator<Item = (isize, isize)>, threshold: u16) -> Self {
+        let offsets = offsets
+            .into_iter()
+            .filter(|&offset| offset != (0, 0))
+            .unique()
+            .collect();
+        Self {
+            threshold,
+            offsets,
+            edges: Edges::default(),
+        }
+    }
+
+    fn with_edges(self, edges: Edges) -> Self {
+        Self { edges, ..self }
+    }
+
+    /// the neighbours of the cell at `pos`, on a floor of the given `(rows, cols)` size
+    fn neighbours(&self, pos: Pos, size: (usize, usize)) -> impl Iterator<Item = Neighbour> {
+        self.offset_all(pos, size, self.offsets.iter().copied())
+    }
+
+    /// the cells which have the cell at `pos` as a neighbour. for all the symmetric neighbourhoods
+    /// this is the same as [`Rule::neighbours`], but custom ones needn't be.
+    fn neighbours_of(&self, pos: Pos, size: (usize, usize)) -> impl Iterator<Item = Neighbour> {
+        self.offset_all(pos, size, self.offsets.iter().map(|&(dx, dy)| (-dx, -dy)))
+    }
+
+    fn offset_all(
+        &self,
+        pos: Pos,
+        size: (usize, usize),
+        offsets: impl Iterator<Item = (isize, isize)>,
+    ) -> impl Iterator<Item = Neighbour> {
+        let edges = self.edges;
+        offsets.filter_map(move |offset| {
+            match (pos.offset(offset, size, edges.boundary()), edges) {
+                (Some(pos), _) => Some(Neighbour::Cell(pos)),
+                (None, Edges::Paper) => Some(Neighbour::Edge),
+                (None, _) => None,
+            }
+        })
+    }
+
+    fn is_accessible(&self, n_adjacent: u16) -> bool {
+        n_adjacent < self.threshold
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::moore_1(Rule::moore(1, 4), 8)]
+#[case::moore_2(Rule::moore(2, 4), 24)]
+#[case::von_neumann_1(Rule::von_neumann(1, 4), 4)]
+#[case::von_neumann_2(Rule::von_neumann(2, 4), 12)]
+#[case::custom_drops_self_and_duplicates(Rule::custom([(0, 0), (0, 1), (0, 1), (1, 0)], 4), 2)]
+fn test_rule_neighbourhood_size(#[case] rule: Rule, #[case] expected: usize) {
+    assert_eq!(expected, rule.neighbours(Pos::new(5, 5), (10, 10)).count());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::bounded_full(Edges::Bounded, "@@@@\n@@@@\n@@@@\n", [[3, 5, 5, 3], [5, 8, 8, 5], [3, 5, 5, 3]])]
+#[case::bounded_corner(Edges::Bounded, "@...\n....\n....\n", [[0, 1, 0, 0], [1, 1, 0, 0], [0, 0, 0, 0]])]
+#[case::bounded_far_corner(Edges::Bounded, "....\n....\n...@\n", [[0, 0, 0, 0], [0, 0, 1, 1], [0, 0, 1, 0]])]
+#[case::wrap_full(Edges::Wrap, "@@@@\n@@@@\n@@@@\n", [[8, 8, 8, 8], [8, 8, 8, 8], [8, 8, 8, 8]])]
+#[case::wrap_corner(Edges::Wrap, "@...\n....\n....\n", [[0, 1, 0, 1], [1, 1, 0, 1], [1, 1, 0, 1]])]
+#[case::wrap_far_corner(Edges::Wrap, "....\n....\n...@\n", [[1, 0, 1, 1], [1, 0, 1, 1], [1, 0, 1, 0]])]
+#[case::paper_full(Edges::Paper, "@@@@\n@@@@\n@@@@\n", [[8, 8, 8, 8], [8, 8, 8, 8], [8, 8, 8, 8]])]
+#[case::paper_empty(Edges::Paper, "....\n....\n....\n", [[5, 3, 3, 5], [3, 0, 0, 3], [5, 3, 3, 5]])]
+fn test_edges(#[case] edges: Edges, #[case] input: &str, #[case] expected: [[u16; 4]; 3]) {
+    let dept = floor(input, Rule::default().with_edges(edges));
+    let Storage::Cells { n_adjacent, .. } = &dept.storage else {
+        unreachable!()
+    };
+    let counts = std::array::from_fn(|x| std::array::from_fn(|y| n_adjacent[Pos::new(x, y)]));
+    assert_eq!(expected, counts);
 }
 
 struct DeptFloor {
-    ground: grid::Grid<CellKind>,
-    accessible: grid::Grid<bool>,
+    rule: Rule,
+    storage: Storage,
+    /// check the cached state against a full recomputation after every change
+    paranoid: bool,
+}
+
+enum Storage {
+    /// a byte per cell, plus a cached neighbour count for every cell
+    Cells {
+        ground: Grid<CellKind>,
+        /// number of paper rolls among each cell's neighbours
+        n_adjacent: Grid<u16>,
+    },
+    /// a bit per cell, with neighbour counts worked out as they're needed
+    Bits(bitboard::Bitboard),
+}
+
+/// a disagreement found by [`DeptFloor::check_consistency`]
+#[derive(Debug, PartialEq, Eq)]
+struct Inconsistency {
+    pos: Pos,
+    what: String,
+    /// the cells surrounding `pos`
+    window: String,
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Pos { row, col } = self.pos;
+        writeln!(
+            f,
+            "floor state diverged at row {row}, column {col}: {}",
+            self.what
+        )?;
+        write!(f, "{}", self.window)
+    }
 }
 
-fn check_accessible(ground: &Grid<CellKind>, (x, y): (usize, usize)) -> bool {
-    surrounding_indices((x, y), 1)
-        .filter_map(|(xx, yy)| ground.get(xx, yy))
-        .filter(|kind| matches!(kind, CellKind::Paper))
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum Backend {
+    #[default]
+    Cells,
+    Bits,
+}
+
+fn count_adjacent_papers(
+    rule: &Rule,
+    size: (usize, usize),
+    pos: Pos,
+    is_paper: impl Fn(Pos) -> bool,
+) -> u16 {
+    rule.neighbours(pos, size)
+        .filter(|neighbour| match neighbour {
+            Neighbour::Cell(pos) => is_paper(*pos),
+            Neighbour::Edge => true,
+        })
         .count()
-        .pipe(|n| n < 4)
+        .pipe(|n| n as u16)
 }
 
 fn solve_pt2(dept: &mut DeptFloor) -> u64 {
+    // every roll enters the queue exactly once: either it's accessible from the start, or it
+    //  becomes accessible when its neighbour count drops below the threshold
+    let mut queue: VecDeque<_> = dept.iter_accessible_papers().collect();
     let mut n = 0;
+    while let Some(to_remove) = queue.pop_front() {
+        queue.extend(dept.remove_paper(to_remove));
+        n += 1;
+    }
+    n
+}
+
+#[cfg(test)]
+const SAMPLE: &str = "\
+..@@.@@@@.
+@@@.@@@.@@
+@@@@@.@.@@
+@.@@@@..@.
+@@.@@@@.@@
+.@@@@@@@.@
+.@.@.@.@@@
+@.@@@.@@@@
+.@@@@@@@@.
+@.@.@@@.@.
+";
+
+#[cfg(test)]
+fn floor(input: &str, rule: Rule) -> DeptFloor {
+    DeptFloor::new(parser::grid(input, false).unwrap(), rule).with_paranoid(true)
+}
+
+#[cfg(test)]
+fn bits_floor(input: &str, rule: Rule) -> DeptFloor {
+    DeptFloor::new_bits(parser::bitboard_str(input, false).unwrap(), rule)
+}
+
+/// a pseudo-random floor, wide enough that rows span multiple words of a [`bitboard::Bitboard`]
+#[cfg(test)]
+fn noisy_floor_input(rows: usize, cols: usize, seed: u64) -> String {
+    let mut state = seed;
+    (0..rows)
+        .map(|_| {
+            (0..cols)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    if state >> 61 < 5 { '@' } else { '.' }
+                })
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE, 44)]
+#[case::all_paper("@@@\n@@@\n@@@\n", 9)]
+#[case::solid_block("@@@@@\n@@@@@\n@@@@@\n@@@@@\n@@@@@\n", 4)]
+#[case::empty("...\n...\n...\n", 0)]
+fn test_solve_pt2(#[case] input: &str, #[case] expected: u64) {
+    let mut dept = floor(input, Rule::default());
+    assert_eq!(expected, solve_pt2(&mut dept));
+    assert_eq!(0, dept.iter_accessible_papers().count());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::moore_2(Rule::moore(2, 9))]
+#[case::von_neumann_1(Rule::von_neumann(1, 3))]
+#[case::asymmetric(Rule::custom([(0, 1), (1, 1), (1, 0), (-2, 0)], 2))]
+#[case::wrap(Rule::default().with_edges(Edges::Wrap))]
+#[case::wrap_large_radius(Rule::moore(6, 100).with_edges(Edges::Wrap))]
+#[case::paper_edges(Rule::default().with_edges(Edges::Paper))]
+fn test_solve_pt2_matches_naive(#[case] rule: Rule) {
+    let mut naive = floor(SAMPLE, rule.clone());
+    let mut n_naive = 0;
     loop {
-        let to_remove = dept.iter_accessible_papers().next();
+        let (rows, cols) = naive.size();
+        let to_remove = (0..rows)
+            .cartesian_product(0..cols)
+            .map(Pos::from)
+            .find(|&pos| naive.cell(pos) == CellKind::Paper && naive.is_accessible(pos));
         match to_remove {
-            Some(to_remove) => {
-                dept.remove_paper(to_remove);
-                n += 1;
+            Some(pos) => {
+                naive.remove_paper(pos);
+                n_naive += 1;
             }
             None => break,
         }
     }
-    n
+    assert_eq!(n_naive, solve_pt2(&mut floor(SAMPLE, rule)));
+}
+
+/// removes rolls in waves, where each wave removes every roll that was accessible at the start of
+/// that wave. `on_wave` is called with the wave number, the rolls it removed, and the floor after
+/// each wave. returns the number of rolls removed by each wave.
+fn solve_waves(
+    dept: &mut DeptFloor,
+    mut on_wave: impl FnMut(usize, &[Pos], &DeptFloor),
+) -> Vec<usize> {
+    let mut wave_sizes = Vec::new();
+    let mut wave: Vec<_> = dept.iter_accessible_papers().collect();
+    while !wave.is_empty() {
+        // rolls which become accessible during this wave make up the next one
+        let next_wave: Vec<_> = wave
+            .iter()
+            .flat_map(|&pos| dept.remove_paper(pos))
+            .collect();
+        wave_sizes.push(wave.len());
+        on_wave(wave_sizes.len(), &wave, dept);
+        wave = next_wave;
+    }
+    wave_sizes
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE, vec![11, 10, 9, 7, 3, 1, 1, 1, 1])]
+#[case::solid_block("@@@@@\n@@@@@\n@@@@@\n@@@@@\n@@@@@\n", vec![4])]
+#[case::empty("...\n...\n...\n", vec![])]
+fn test_solve_waves(#[case] input: &str, #[case] expected: Vec<usize>) {
+    let mut dept = floor(input, Rule::default());
+    let mut n_frames = 0;
+    assert_eq!(expected, solve_waves(&mut dept, |_, _, _| n_frames += 1));
+    assert_eq!(expected.len(), n_frames);
+}
+
+#[cfg(test)]
+#[test]
+fn test_check_consistency_reports_divergence() {
+    let mut dept = floor("@@@\n@@@\n@@.\n", Rule::default()).with_paranoid(false);
+    assert_eq!(Ok(()), dept.check_consistency());
+    if let Storage::Cells { n_adjacent, .. } = &mut dept.storage {
+        n_adjacent[Pos::new(2, 1)] += 1;
+    }
+    assert_eq!(
+        Err(Inconsistency {
+            pos: Pos::new(2, 1),
+            what: "cached neighbour count 5, recomputed 4".into(),
+            window: "@@@\n@@.\n   ".into(),
+        }),
+        dept.check_consistency()
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::default(Rule::default())]
+#[case::moore_2(Rule::moore(2, 9))]
+#[case::von_neumann_3(Rule::von_neumann(3, 12))]
+#[case::asymmetric(Rule::custom([(0, 1), (1, 1), (1, 0), (-2, 0), (0, -70)], 2))]
+#[case::wrap(Rule::default().with_edges(Edges::Wrap))]
+#[case::wrap_moore_2(Rule::moore(2, 10).with_edges(Edges::Wrap))]
+#[case::paper_edges(Rule::default().with_edges(Edges::Paper))]
+#[case::paper_edges_moore_2(Rule::moore(2, 14).with_edges(Edges::Paper))]
+fn test_bits_matches_cells(
+    #[case] rule: Rule,
+    #[values((10, 10), (7, 64), (9, 130), (3, 200))] (rows, cols): (usize, usize),
+) {
+    let input = noisy_floor_input(rows, cols, (rows * cols) as u64);
+    let mut cells = floor(&input, rule.clone()).with_paranoid(false);
+    let mut bits = bits_floor(&input, rule);
+    assert_eq!(cells.to_string(), bits.to_string());
+    assert_eq!(cells.n_accessible_rolls(), bits.n_accessible_rolls());
+    assert_eq!(
+        cells.iter_accessible_papers().collect::<Vec<_>>(),
+        bits.iter_accessible_papers().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        solve_waves(&mut cells, |_, _, _| ()),
+        solve_waves(&mut bits, |_, _, _| ())
+    );
+    assert_eq!(cells.to_string(), bits.to_string());
+}
+
+impl Display for DeptFloor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (rows, cols) = self.size();
+        for x in 0..rows {
+            if x > 0 {
+                writeln!(f)?;
+            }
+            for y in 0..cols {
+                write!(f, "{}", self.cell(Pos::new(x, y)))?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DeptFloor {
-    fn parse(input: &str) -> Result<Self, ()> {
-        let ground = parser::grid.parse(input).map_err(|_| ())?;
-        let mut accessible =
-            Grid::new_with_order(ground.rows(), ground.cols(), ground.order());
-        accessible.indexed_iter_mut().for_each(|(pos, val)| {
-            *val = check_accessible(&ground, pos);
+    fn new(ground: Grid<CellKind>, rule: Rule) -> Self {
+        let n_adjacent = Grid::from_fn(ground.rows(), ground.cols(), |pos| {
+            count_adjacent_papers(&rule, ground.size(), pos, |pos| {
+                ground[pos] == CellKind::Paper
+            })
         });
-        Ok(Self { ground, accessible })
+        Self {
+            rule,
+            storage: Storage::Cells { ground, n_adjacent },
+            paranoid: false,
+        }
+    }
+
+    fn new_bits(ground: bitboard::Bitboard, rule: Rule) -> Self {
+        Self {
+            rule,
+            storage: Storage::Bits(ground),
+            paranoid: false,
+        }
+    }
+
+    /// see [`DeptFloor::check_consistency`]. this makes every removal take time proportional to
+    /// the size of the whole floor, so is only for hunting down bugs.
+    fn with_paranoid(self, paranoid: bool) -> Self {
+        let floor = Self { paranoid, ..self };
+        floor.assert_consistent();
+        floor
+    }
+
+    /// checks whatever's derived incrementally against working it all out again from scratch:
+    /// the cached neighbour counts for the cells backend, or the word-at-a-time accessibility
+    /// for the bits backend.
+    fn check_consistency(&self) -> Result<(), Inconsistency> {
+        let (rows, cols) = self.size();
+        for x in 0..rows {
+            let bits_accessible = match &self.storage {
+                Storage::Bits(bits) => Some(bits.accessible_row(x, &self.rule)),
+                Storage::Cells { .. } => None,
+            };
+            for y in 0..cols {
+                let pos = Pos::new(x, y);
+                let recomputed = self.count_adjacent_papers(pos);
+                let diverged = match &self.storage {
+                    Storage::Cells { n_adjacent, .. } => {
+                        let cached = n_adjacent[pos];
+                        (cached != recomputed).then(|| {
+                            format!("cached neighbour count {cached}, recomputed {recomputed}")
+                        })
+                    }
+                    Storage::Bits(_) => {
+                        let accessible = bits_accessible.as_deref().unwrap();
+                        let fast = accessible[y / 64] >> (y % 64) & 1 == 1;
+                        let slow = self.cell(pos) == CellKind::Paper
+                            && self.rule.is_accessible(recomputed);
+                        (fast != slow).then(|| {
+                            format!("accessible {fast} word-at-a-time, but {slow} cell-by-cell")
+                        })
+                    }
+                };
+                if let Some(what) = diverged {
+                    return Err(Inconsistency {
+                        pos,
+                        what,
+                        window: self.window(pos),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn assert_consistent(&self) {
+        if self.paranoid
+            && let Err(err) = self.check_consistency()
+        {
+            panic!("{err}");
+        }
+    }
+
+    /// the 3×3 cells around `pos`, with anything past the edges left blank
+    fn window(&self, pos: Pos) -> String {
+        (-1..=1)
+            .map(|dx| {
+                (-1..=1)
+                    .map(
+                        |dy| match pos.offset((dx, dy), self.size(), Boundary::Bounded) {
+                            Some(pos) => self.cell(pos).to_string(),
+                            None => " ".into(),
+                        },
+                    )
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+
+    /// `(rows, cols)`
+    fn size(&self) -> (usize, usize) {
+        match &self.storage {
+            Storage::Cells { ground, .. } => ground.size(),
+            Storage::Bits(bits) => bits.size(),
+        }
+    }
+
+    fn cell(&self, pos: Pos) -> CellKind {
+        match &self.storage {
+            Storage::Cells { ground, .. } => ground[pos],
+            Storage::Bits(bits) => match bits.get(pos) {
+                true => CellKind::Paper,
+                false => CellKind::Empty,
+            },
+        }
     }
 
-    fn n_adjacent_papers_at(&self, x: usize, y: usize) -> usize {
-        (x.saturating_sub(1)..=(x + 1))
-            .cartesian_product(y.saturating_sub(1)..=(y + 1))
-            .filter(|&(ax, ay)| ax != x || ay != y)
-            .filter_map(|(ax, ay)| self.ground.get(ax, ay))
-            .filter(|kind| matches!(kind, CellKind::Paper))
-            .count()
+    fn count_adjacent_papers(&self, pos: Pos) -> u16 {
+        count_adjacent_papers(&self.rule, self.size(), pos, |pos| {
+            self.cell(pos) == CellKind::Paper
+        })
     }
 
-    fn is_accessible(&self, x: usize, y: usize) -> bool {
-        self.n_adjacent_papers_at(x, y) < 4
+    fn is_accessible(&self, pos: Pos) -> bool {
+        self.rule.is_accessible(self.count_adjacent_papers(pos))
     }
 
     fn n_accessible_rolls(&self) -> usize {
-        self.ground
-            .indexed_iter()
-            .filter(|&((x, y), kind)| matches!(kind, CellKind::Paper) && self.is_accessible(x, y))
-            .count()
+        match &self.storage {
+            Storage::Cells { ground, .. } => ground
+                .iter()
+                .filter(|&(pos, kind)| matches!(kind, CellKind::Paper) && self.is_accessible(pos))
+                .count(),
+            Storage::Bits(bits) => (0..bits.rows())
+                .map(|x| {
+                    bits.accessible_row(x, &self.rule)
+                        .iter()
+                        .map(|word| word.count_ones() as usize)
+                        .sum::<usize>()
+                })
+                .sum(),
+        }
     }
 
-    fn remove_paper(&mut self, (x, y): (usize, usize)) {
-        debug_assert!(matches!(self.ground.get(x, y), Some(CellKind::Paper)));
-        self.ground[(x, y)] = CellKind::Empty;
-        for (x, y) in surrounding_indices((x, y), 1) {
-            if let Some(v) = self.accessible.get_mut(x, y) {
-                *v = check_accessible(&self.ground, (x, y));
+    /// removes the paper roll at the given position, returning the positions of any rolls which
+    /// became accessible as a result.
+    fn remove_paper(&mut self, pos: Pos) -> Vec<Pos> {
+        debug_assert_eq!(CellKind::Paper, self.cell(pos));
+        let size = self.size();
+        let mut newly_accessible = Vec::new();
+        match &mut self.storage {
+            Storage::Cells { ground, n_adjacent } => {
+                ground[pos] = CellKind::Empty;
+                for neighbour in self.rule.neighbours_of(pos, size) {
+                    // the edges never change, so don't need updating
+                    let Neighbour::Cell(pos) = neighbour else {
+                        continue;
+                    };
+                    let n = &mut n_adjacent[pos];
+                    *n -= 1;
+                    // only report a roll the moment it crosses the threshold, so that it's never
+                    //  reported twice
+                    if *n + 1 == self.rule.threshold && matches!(ground[pos], CellKind::Paper) {
+                        newly_accessible.push(pos);
+                    }
+                }
+            }
+            Storage::Bits(bits) => {
+                bits.set(pos, false);
+                // without cached counts, a roll crossed the threshold if it's below it now, but
+                //  wouldn't have been before losing however many neighbours it just lost (which
+                //  can be more than one, if the floor wraps round onto itself)
+                let lost = self
+                    .rule
+                    .neighbours_of(pos, size)
+                    .filter_map(|neighbour| match neighbour {
+                        Neighbour::Cell(pos) => Some(pos),
+                        Neighbour::Edge => None,
+                    })
+                    .counts();
+                for (pos, n_lost) in lost {
+                    if self.cell(pos) != CellKind::Paper {
+                        continue;
+                    }
+                    let n = self.count_adjacent_papers(pos);
+                    if self.rule.is_accessible(n) && !self.rule.is_accessible(n + n_lost as u16) {
+                        newly_accessible.push(pos);
+                    }
+                }
             }
         }
+        self.assert_consistent();
+        newly_accessible
     }
 
-    fn iter_accessible_papers(&self) -> impl Iterator<Item = (usize, usize)> {
-        self.ground
-            .indexed_iter()
-            .filter(|(_, kind)| matches!(kind, CellKind::Paper))
-            .filter(|(pos, _)| self.accessible.get(pos.0, pos.1).copied().unwrap())
-            .map(|(pos, _)| pos)
+    fn iter_accessible_papers(&self) -> impl Iterator<Item = Pos> {
+        match &self.storage {
+            Storage::Cells { ground, n_adjacent } => ground
+                .iter()
+                .filter(|(_, kind)| matches!(kind, CellKind::Paper))
+                .filter(|(pos, _)| self.rule.is_accessible(n_adjacent[*pos]))
+                .map(|(pos, _)| pos)
+                .pipe(itertools::Either::Left),
+            Storage::Bits(bits) => (0..bits.rows())
+                .flat_map(|x| {
+                    bits.accessible_row(x, &self.rule)
+                        .into_iter()
+                        .enumerate()
+                        .flat_map(move |(word_idx, mut word)| {
+                            std::iter::from_fn(move || {
+                                (word != 0).then(|| {
+                                    let bit = word.trailing_zeros() as usize;
+                                    word &= word - 1;
+                                    Pos::new(x, word_idx * 64 + bit)
+                                })
+                            })
+                        })
+                })
+                .pipe(itertools::Either::Right),
+        }
     }
 }
 
-mod parser {
-    use super::CellKind;
-    use grid::Grid;
-    use winnow::{
-        ModalResult, Parser,
-        ascii::line_ending,
-        combinator::{alt, eof, repeat, terminated},
-    };
+mod bitboard {
+    use super::{Edges, Rule};
+    use common::grid::Pos;
+
+    /// a floor packed a bit per cell, with each row starting on a fresh word, and any bits past
+    /// the end of a row always kept clear.
+    pub struct Bitboard {
+        rows: usize,
+        cols: usize,
+        words_per_row: usize,
+        words: Vec<u64>,
+    }
+
+    impl Bitboard {
+        pub fn new(rows: usize, cols: usize) -> Self {
+            let words_per_row = cols.div_ceil(64);
+            Self {
+                rows,
+                cols,
+                words_per_row,
+                words: vec![0; rows * words_per_row],
+            }
+        }
+
+        /// adds a row to the bottom of the floor, with the given cells from the left. any cells
+        /// past the end of them are empty.
+        pub fn push_row(&mut self, cells: impl IntoIterator<Item = bool>) {
+            let start = self.words.len();
+            self.words.resize(start + self.words_per_row, 0);
+            for (col, paper) in cells.into_iter().enumerate() {
+                debug_assert!(col < self.cols);
+                self.words[start + col / 64] |= (paper as u64) << (col % 64);
+            }
+            self.rows += 1;
+        }
+
+        pub fn rows(&self) -> usize {
+            self.rows
+        }
+
+        pub fn size(&self) -> (usize, usize) {
+            (self.rows, self.cols)
+        }
+
+        pub fn get(&self, Pos { row, col }: Pos) -> bool {
+            debug_assert!(row < self.rows && col < self.cols);
+            self.row(row)[col / 64] >> (col % 64) & 1 == 1
+        }
+
+        pub fn set(&mut self, Pos { row, col }: Pos, value: bool) {
+            debug_assert!(row < self.rows && col < self.cols);
+            let word = &mut self.words[row * self.words_per_row + col / 64];
+            match value {
+                true => *word |= 1 << (col % 64),
+                false => *word &= !(1 << (col % 64)),
+            }
+        }
+
+        fn row(&self, x: usize) -> &[u64] {
+            &self.words[x * self.words_per_row..][..self.words_per_row]
+        }
+
+        /// mask of the bits in the last word of each row which are actually on the floor
+        fn last_word_mask(&self) -> u64 {
+            match self.cols % 64 {
+                0 => !0,
+                n => (1 << n) - 1,
+            }
+        }
+
+        /// the cell at the given position, which may be past the edges of the floor
+        fn get_unbounded(&self, pos: (isize, isize), edges: Edges) -> bool {
+            match Pos::default().offset(pos, self.size(), edges.boundary()) {
+                Some(pos) => self.get(pos),
+                None => edges == Edges::Paper,
+            }
+        }
+
+        /// row `x + dx`, shifted so that bit `y` of the result is the cell at column `y + dy`.
+        /// anything past the edges of the floor is filled in according to `edges`.
+        fn shifted_row(&self, x: usize, (dx, dy): (isize, isize), edges: Edges) -> Vec<u64> {
+            let xx = x as isize + dx;
+            let mut out: Vec<u64> = (0..self.words_per_row)
+                .map(|word_idx| {
+                    let start = (word_idx * 64) as isize + dy;
+                    let in_bounds = (0..self.rows as isize).contains(&xx)
+                        && start >= 0
+                        && start + 64 <= self.cols as isize;
+                    if in_bounds {
+                        // the whole word comes from within this row, so can be read directly
+                        let row = self.row(xx as usize);
+                        let (idx, offset) = (start as usize / 64, start as usize % 64);
+                        match offset {
+                            0 => row[idx],
+                            _ => row[idx] >> offset | row[idx + 1] << (64 - offset),
+                        }
+                    } else {
+                        (0..64).fold(0, |word, bit| {
+                            let cell = self.get_unbounded((xx, start + bit), edges);
+                            word | (cell as u64) << bit
+                        })
+                    }
+                })
+                .collect();
+            if let Some(last) = out.last_mut() {
+                *last &= self.last_word_mask();
+            }
+            out
+        }
+
+        /// which cells in row `x` hold accessible rolls, as a bit per cell.
+        ///
+        /// neighbour counts are kept as bit-sliced binary numbers (one word of counter bit `n`
+        /// for each word of the row), with each neighbour's shifted row added in with a
+        /// ripple-carry adder, so every word is handled 64 cells at a time.
+        pub fn accessible_row(&self, x: usize, rule: &Rule) -> Vec<u64> {
+            let max_count = rule.offsets.len().max(rule.threshold as usize);
+            let n_planes = (usize::BITS - max_count.leading_zeros()) as usize;
+            let mut planes = vec![vec![0u64; self.words_per_row]; n_planes];
+
+            for &offset in &rule.offsets {
+                for (idx, mut carry) in self
+                    .shifted_row(x, offset, rule.edges)
+                    .into_iter()
+                    .enumerate()
+                {
+                    for plane in &mut planes {
+                        if carry == 0 {
+                            break;
+                        }
+                        let sum = plane[idx] ^ carry;
+                        carry &= plane[idx];
+                        plane[idx] = sum;
+                    }
+                }
+            }
+
+            self.row(x)
+                .iter()
+                .enumerate()
+                .map(|(idx, &paper)| {
+                    // compare the counts against the threshold, from the most significant bit
+                    //  down, tracking which cells are already known to be less than it, and
+                    //  which are equal to it so far
+                    let (mut less, mut equal) = (0u64, !0u64);
+                    for (bit, plane) in planes.iter().enumerate().rev() {
+                        let count_bits = plane[idx];
+                        match rule.threshold >> bit & 1 {
+                            1 => {
+                                less |= equal & !count_bits;
+                                equal &= count_bits;
+                            }
+                            _ => equal &= !count_bits,
+                        }
+                    }
+                    paper & less
+                })
+                .collect()
+        }
+    }
+}
+
+mod render {
+    use super::{CellKind, DeptFloor};
+    use common::grid::{Grid, Pos};
+    #[cfg(test)]
+    use rstest::rstest;
+    use std::path::Path;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Tile {
+        Empty,
+        Paper,
+        /// accessible at the start
+        Accessible,
+        /// removed later on, optionally by a numbered wave
+        Removed {
+            wave: Option<usize>,
+        },
+    }
+
+    /// what's happened to each cell of a floor over the course of removing rolls from it
+    pub struct Tiles {
+        tiles: Grid<Tile>,
+        n_waves: usize,
+    }
+
+    impl Tiles {
+        /// starts tracking from the floor's current state
+        pub fn new(floor: &DeptFloor) -> Self {
+            let (rows, cols) = floor.size();
+            let mut tiles = Grid::new(rows, cols, Tile::Empty);
+            for (pos, tile) in tiles.iter_mut() {
+                if floor.cell(pos) == CellKind::Paper {
+                    *tile = Tile::Paper;
+                }
+            }
+            for pos in floor.iter_accessible_papers() {
+                tiles[pos] = Tile::Accessible;
+            }
+            Self { tiles, n_waves: 0 }
+        }
+
+        /// rolls which were accessible from the start keep showing as such, rather than as
+        /// removed.
+        fn mark_removed(&mut self, pos: Pos, wave: Option<usize>) {
+            if self.tiles[pos] == Tile::Paper {
+                self.tiles[pos] = Tile::Removed { wave };
+            }
+        }
 
-    fn cell(input: &mut &str) -> ModalResult<CellKind> {
-        alt(('@'.value(CellKind::Paper), '.'.value(CellKind::Empty))).parse_next(input)
+        pub fn record_wave(&mut self, wave: usize, removed: &[Pos]) {
+            self.n_waves = self.n_waves.max(wave);
+            for &pos in removed {
+                self.mark_removed(pos, Some(wave));
+            }
+        }
+
+        /// marks any paper which has since been removed from the floor, without knowing when
+        pub fn record_removed_since_start(&mut self, floor: &DeptFloor) {
+            for pos in self.tiles.positions() {
+                if floor.cell(pos) == CellKind::Empty {
+                    self.mark_removed(pos, None);
+                }
+            }
+        }
+
+        fn colour(&self, tile: Tile, shade_waves: bool) -> [u8; 3] {
+            match tile {
+                Tile::Empty => [32, 32, 40],
+                Tile::Paper => [230, 226, 210],
+                Tile::Accessible => [70, 190, 90],
+                Tile::Removed { wave: Some(wave) } if shade_waves && self.n_waves > 1 => {
+                    // fade from bright orange for the earliest waves to dark red for the last
+                    let t = (wave - 1) as f64 / (self.n_waves - 1) as f64;
+                    let lerp = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+                    [lerp(250.0, 110.0), lerp(170.0, 20.0), lerp(40.0, 30.0)]
+                }
+                Tile::Removed { .. } => [210, 70, 50],
+            }
+        }
+
+        /// draws each cell as a `scale`×`scale` square
+        pub fn image(&self, shade_waves: bool, scale: usize) -> Image {
+            let (rows, cols) = self.tiles.size();
+            let (width, height) = (cols * scale, rows * scale);
+            let mut pixels = Vec::with_capacity(width * height * 3);
+            for row in self.tiles.iter_rows() {
+                let row: Vec<_> = row
+                    .iter()
+                    .flat_map(|&tile| {
+                        std::iter::repeat_n(self.colour(tile, shade_waves), scale).flatten()
+                    })
+                    .collect();
+                for _ in 0..scale {
+                    pixels.extend_from_slice(&row);
+                }
+            }
+            Image {
+                width,
+                height,
+                pixels,
+            }
+        }
     }
 
-    pub fn grid(input: &mut &str) -> ModalResult<Grid<CellKind>> {
-        // parse first row
-        let first_row: Vec<_> =
-            terminated(repeat(1.., cell), alt((line_ending, eof))).parse_next(input)?;
-        let width = first_row.len();
+    /// 8-bit rgb, row by row
+    pub struct Image {
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    }
 
-        // extend existing vec to hold entire grid
-        let mut data = first_row;
-        data.reserve_exact(width * (width - 1));
+    /// the formats an [`Image`] can be saved in
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Format {
+        Ppm,
+        Png,
+    }
 
-        // parse the remaining rows
-        for _ in 2..=width {
-            for _ in 0..width {
-                data.push(cell.parse_next(input)?);
+    impl Format {
+        /// picks the format from the file extension
+        pub fn of(path: &Path) -> std::io::Result<Self> {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("ppm") => Ok(Format::Ppm),
+                Some("png") if cfg!(feature = "png") => Ok(Format::Png),
+                Some("png") => Err(std::io::Error::other(
+                    "png output needs the `png` feature; use a .ppm file instead",
+                )),
+                _ => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "don't know what format to write `{}` in; use a .ppm or .png file",
+                        path.display()
+                    ),
+                )),
             }
-            alt((line_ending, eof)).void().parse_next(input)?;
         }
-        let grid = Grid::from_vec_with_order(data, width, grid::Order::RowMajor);
-        Ok(grid)
+    }
+
+    impl Image {
+        /// picks the format from the file extension
+        pub fn save(&self, path: &Path) -> std::io::Result<()> {
+            let format = Format::of(path)?;
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            match format {
+                Format::Ppm => self.write_ppm(file),
+                Format::Png => self.write_png(file),
+            }
+        }
+
+        fn write_ppm(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+            write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+            w.write_all(&self.pixels)?;
+            w.flush()
+        }
+
+        #[cfg(feature = "png")]
+        fn write_png(&self, w: impl std::io::Write) -> std::io::Result<()> {
+            let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&self.pixels))
+                .map_err(std::io::Error::other)
+        }
+
+        #[cfg(not(feature = "png"))]
+        fn write_png(&self, _w: impl std::io::Write) -> std::io::Result<()> {
+            unreachable!("checked for in `Format::of`")
+        }
+    }
+
+    #[cfg(test)]
+    #[rstest]
+    #[case::plain(false, [210, 70, 50], [210, 70, 50])]
+    #[case::shaded(true, [180, 95, 35], [110, 20, 30])]
+    fn test_render_waves(
+        #[case] shade_waves: bool,
+        #[case] edge: [u8; 3],
+        #[case] centre: [u8; 3],
+    ) {
+        // the corners are accessible from the start, then the edges go in wave 2, and the centre
+        //  in wave 3
+        let mut floor = super::floor("@@@\n@@@\n@@@\n", super::Rule::default());
+        let mut tiles = Tiles::new(&floor);
+        let pixel = |image: &Image, x: usize, y: usize| {
+            <[u8; 3]>::try_from(&image.pixels[(x * image.width + y) * 3..][..3]).unwrap()
+        };
+        assert_eq!([230, 226, 210], pixel(&tiles.image(shade_waves, 1), 1, 1));
+
+        super::solve_waves(&mut floor, |wave, removed, _| {
+            tiles.record_wave(wave, removed)
+        });
+        let image = tiles.image(shade_waves, 1);
+        assert_eq!([70, 190, 90], pixel(&image, 0, 0));
+        assert_eq!(edge, pixel(&image, 0, 1));
+        assert_eq!(centre, pixel(&image, 1, 1));
+    }
+
+    #[cfg(test)]
+    #[rstest]
+    #[case::ppm("floor.ppm", Some(Format::Ppm))]
+    #[case::png("floor.png", cfg!(feature = "png").then_some(Format::Png))]
+    #[case::unknown("floor.gif", None)]
+    #[case::no_extension("floor", None)]
+    fn test_format(#[case] path: &str, #[case] expected: Option<Format>) {
+        assert_eq!(expected, Format::of(Path::new(path)).ok());
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn test_write_ppm() {
+        let floor = super::floor("@.\n..\n", super::Rule::default());
+        let mut out = Vec::new();
+        Tiles::new(&floor)
+            .image(false, 2)
+            .write_ppm(&mut out)
+            .unwrap();
+        let (header, pixels) = out.split_at(b"P6\n4 4\n255\n".len());
+        assert_eq!(b"P6\n4 4\n255\n", header);
+        assert_eq!(4 * 4 * 3, pixels.len());
+        assert_eq!([70, 190, 90, 70, 190, 90, 32, 32, 40], pixels[..9]);
+    }
+}
+
+mod parser {
+    use super::{CellKind, bitboard::Bitboard};
+    use common::grid::{Grid, ParseError, parse_row};
+    #[cfg(test)]
+    use rstest::rstest;
+
+    fn cell(c: char) -> Option<CellKind> {
+        match c {
+            '@' => Some(CellKind::Paper),
+            '.' => Some(CellKind::Empty),
+            _ => None,
+        }
+    }
+
+    /// like [`grid`], but reads the floor a line at a time, packing each row straight into a bit
+    /// per cell, so that the floor is never held as text. with `pad_to`, rows may be up to that
+    /// wide, short ones being filled out with empty cells. otherwise every row must be as wide as
+    /// the first.
+    pub fn bitboard<S: AsRef<str>>(
+        lines: impl IntoIterator<Item = S>,
+        pad_to: Option<usize>,
+    ) -> Result<Bitboard, ParseError> {
+        let mut lines = lines.into_iter().peekable();
+        let width = match pad_to {
+            Some(width) => width,
+            None => lines.peek().map_or(0, |line| line.as_ref().chars().count()),
+        };
+        if width == 0 {
+            return Err(ParseError::Empty);
+        }
+
+        let mut bits = Bitboard::new(0, width);
+        for (x, line) in lines.enumerate() {
+            let row = parse_row(x, line.as_ref(), cell)?;
+            let ragged = match pad_to {
+                Some(_) => row.len() > width,
+                None => row.len() != width,
+            };
+            if ragged {
+                return Err(ParseError::RaggedRow {
+                    row: x + 1,
+                    expected: width,
+                    found: row.len(),
+                });
+            }
+            bits.push_row(row.into_iter().map(|kind| kind == CellKind::Paper));
+        }
+        Ok(bits)
+    }
+
+    /// the number of cells in the widest line, to pad every row out to
+    pub fn max_width<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> usize {
+        lines
+            .into_iter()
+            .map(|line| line.as_ref().chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// [`bitboard`] from a whole input held in memory
+    #[cfg(test)]
+    pub fn bitboard_str(input: &str, pad: bool) -> Result<Bitboard, ParseError> {
+        bitboard(input.lines(), pad.then(|| max_width(input.lines())))
+    }
+
+    /// parses a grid of any number of equal-width rows. if `pad` is set, rows may instead be of
+    /// differing widths, with short rows treated as though they're filled out with empty cells.
+    pub fn grid(input: &str, pad: bool) -> Result<Grid<CellKind>, ParseError> {
+        match pad {
+            false => Grid::parse(input, cell),
+            true => Grid::parse_padded(input, cell, CellKind::Empty),
+        }
+    }
+
+    #[cfg(test)]
+    #[rstest]
+    #[case::square("@.\n.@\n", false, 2, 2)]
+    #[case::no_trailing_newline("@.\n.@", false, 2, 2)]
+    #[case::crlf("@.\r\n.@\r\n", false, 2, 2)]
+    #[case::wide("@.@.@.@.@.\n..........\n", false, 2, 10)]
+    #[case::tall("@\n.\n@\n.\n@\n", false, 5, 1)]
+    #[case::padded("@@@\n@\n@@\n", true, 3, 3)]
+    fn test_grid_dimensions(
+        #[case] input: &str,
+        #[case] pad: bool,
+        #[case] rows: usize,
+        #[case] cols: usize,
+    ) {
+        let grid = grid(input, pad).unwrap();
+        assert_eq!((rows, cols), (grid.rows(), grid.cols()));
+        assert_eq!((rows, cols), bitboard_str(input, pad).unwrap().size());
+    }
+
+    #[cfg(test)]
+    #[rstest]
+    #[case::short_row("@@@\n@@@\n@@\n@@@\n", 3, 3, 2)]
+    #[case::long_row("@@@\n@@@@\n", 2, 3, 4)]
+    #[case::blank_line("@@@\n\n@@@\n", 2, 3, 0)]
+    fn test_grid_ragged_row(
+        #[case] input: &str,
+        #[case] row: usize,
+        #[case] expected: usize,
+        #[case] found: usize,
+    ) {
+        assert_eq!(
+            Err(ParseError::RaggedRow {
+                row,
+                expected,
+                found
+            }),
+            grid(input, false)
+        );
+        assert_eq!(
+            Some(ParseError::RaggedRow {
+                row,
+                expected,
+                found
+            }),
+            bitboard_str(input, false).err()
+        );
+    }
+
+    #[cfg(test)]
+    #[rstest]
+    #[case::unknown("@@@\n@x@\n", 2, 2, 'x')]
+    #[case::non_ascii("@é@\n@@@\n", 1, 2, 'é')]
+    #[case::after_non_ascii("é@\n", 1, 1, 'é')]
+    #[case::non_ascii_past_ascii("@@@\n@@é\n", 2, 3, 'é')]
+    fn test_grid_unknown_cell(
+        #[case] input: &str,
+        #[case] row: usize,
+        #[case] col: usize,
+        #[case] found: char,
+    ) {
+        let expected = Some(ParseError::UnknownCell { row, col, found });
+        assert_eq!(expected, grid(input, false).err());
+        assert_eq!(expected, bitboard_str(input, false).err());
+    }
+
+    /// read a line at a time, as the bits backend does from a file
+    #[cfg(test)]
+    #[rstest]
+    #[case::lf("@.@\n.@.\n", None, (2, 3))]
+    #[case::crlf("@.@\r\n.@.\r\n", None, (2, 3))]
+    #[case::padded("@\n.@.\n", Some(3), (2, 3))]
+    fn test_bitboard_from_reader(
+        #[case] input: &str,
+        #[case] pad_to: Option<usize>,
+        #[case] size: (usize, usize),
+    ) {
+        use common::grid::Pos;
+        use std::io::BufRead as _;
+        let lines = std::io::Cursor::new(input).lines().map(Result::unwrap);
+        let bits = bitboard(lines, pad_to).unwrap();
+        assert_eq!(size, bits.size());
+        assert!(bits.get(Pos::new(1, 1)));
+        assert!(!bits.get(Pos::new(1, 2)));
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn test_grid_padding_is_empty() {
+        let grid = grid("@@@\n@\n", true).unwrap();
+        assert_eq!(
+            vec![CellKind::Paper, CellKind::Empty, CellKind::Empty],
+            grid.row(1)
+        );
     }
 }
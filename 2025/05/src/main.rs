@@ -1,77 +1,615 @@
 #![allow(unused)]
 
-use std::cmp::{max, min};
+#[cfg(test)]
+use std::ops::RangeInclusive;
+use std::{
+    io::{BufRead, Write as _},
+    path::{Path, PathBuf},
+};
 
+use clap::Parser as _;
+use range_set::RangeSet;
+#[cfg(test)]
+use rstest::rstest;
+use tap::Pipe as _;
 use winnow::Parser as _;
 
+#[derive(clap::Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(default_value = "sample.txt")]
+    input_file: PathBuf,
+    /// merge ranges which touch without overlapping, like `3-5` and `6-8`
+    #[arg(long, global = true)]
+    merge_adjacent: bool,
+}
+
+/// operations on the fresh ranges of input files, which print the resulting ranges. the files'
+/// lists of available IDs, if they have them, are ignored.
+#[derive(clap::Subcommand)]
+enum Command {
+    /// IDs fresh in either file
+    Union { a: PathBuf, b: PathBuf },
+    /// IDs fresh in both files
+    Intersection { a: PathBuf, b: PathBuf },
+    /// IDs fresh in the first file but not the second
+    Difference { a: PathBuf, b: PathBuf },
+    /// IDs which aren't fresh, from `lo` to `hi` (by default, the file's lowest and highest fresh
+    /// IDs)
+    Complement {
+        file: PathBuf,
+        #[arg(long)]
+        lo: Option<u64>,
+        #[arg(long)]
+        hi: Option<u64>,
+    },
+    /// load the fresh ranges from `ranges`, then look up each ID (one per line) from `ids`, or
+    /// stdin if not given, printing whether it's fresh and which range it's in as it goes
+    Query {
+        ranges: PathBuf,
+        ids: Option<PathBuf>,
+    },
+}
+
+/// the result of looking up one ID, like `5 fresh 3-5` or `8 spoiled`
+fn query_result(fresh_ranges: &RangeSet<u64>, id: u64) -> String {
+    match fresh_ranges.range_containing(&id) {
+        Some(range) => format!("{id} fresh {}-{}", range.start(), range.end()),
+        None => format!("{id} spoiled"),
+    }
+}
+
+/// answers queries a line at a time, so that results come out as soon as each ID comes in. lines
+/// which aren't IDs are reported on stderr and skipped, rather than ending the whole stream.
+fn run_queries(fresh_ranges: &RangeSet<u64>, ids: impl BufRead) {
+    let mut stdout = std::io::stdout().lock();
+    for (line_no, line) in ids.lines().enumerate() {
+        let line = line.expect("reading IDs failed");
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse::query_id.parse(line.trim()) {
+            Ok(id) => {
+                writeln!(stdout, "{}", query_result(fresh_ranges, id)).expect("writing failed");
+            }
+            Err(_) => eprintln!("line {}: not an ID: {line:?}", line_no + 1),
+        }
+    }
+}
+
+fn read_fresh_ranges(path: &Path, merge_adjacent: bool) -> RangeSet<u64> {
+    let txt = std::fs::read_to_string(path).expect("reading input file failed");
+    let mut set = RangeSet::new().with_adjacent_merging(merge_adjacent);
+    for range in parse::fresh_ranges
+        .parse(txt.as_str())
+        .expect("failed to parse input")
+    {
+        set.insert(range);
+    }
+    set
+}
+
 fn main() {
-    let input_file = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "sample.txt".into());
-    let txt = std::fs::read_to_string(input_file).expect("reading input file failed");
+    let args = Args::parse();
+    let read_fresh_ranges = |path: &Path| read_fresh_ranges(path, args.merge_adjacent);
+    if let Some(command) = args.command {
+        let result = match command {
+            Command::Query { ranges, ids } => {
+                let fresh_ranges = read_fresh_ranges(&ranges);
+                match ids {
+                    Some(path) => std::fs::File::open(path)
+                        .expect("opening IDs file failed")
+                        .pipe(std::io::BufReader::new)
+                        .pipe(|ids| run_queries(&fresh_ranges, ids)),
+                    None => run_queries(&fresh_ranges, std::io::stdin().lock()),
+                }
+                return;
+            }
+            Command::Union { a, b } => read_fresh_ranges(&a).union(&read_fresh_ranges(&b)),
+            Command::Intersection { a, b } => {
+                read_fresh_ranges(&a).intersection(&read_fresh_ranges(&b))
+            }
+            Command::Difference { a, b } => {
+                read_fresh_ranges(&a).difference(&read_fresh_ranges(&b))
+            }
+            Command::Complement { file, lo, hi } => {
+                let set = read_fresh_ranges(&file);
+                // an empty set has no bounds of its own, so default to an empty range
+                let (default_lo, default_hi) = set.bounds().map_or((1, 0), |b| b.into_inner());
+                set.complement_within(lo.unwrap_or(default_lo)..=hi.unwrap_or(default_hi))
+            }
+        };
+        if !result.is_empty() {
+            println!("{result}");
+        }
+        return;
+    }
+
+    let txt = std::fs::read_to_string(args.input_file).expect("reading input file failed");
     let (fresh_ranges, active_ids) = parse::full_input
         .parse(txt.as_str())
         .expect("failed to parse input");
+    let fresh_ranges = RangeSet::new()
+        .with_adjacent_merging(args.merge_adjacent)
+        .pipe(|mut set| {
+            set.extend(fresh_ranges);
+            set
+        });
 
     let pt1_answer = active_ids
         .iter()
-        .filter(|id| fresh_ranges.iter().any(|range| range.contains(id)))
+        .filter(|id| fresh_ranges.contains(id))
         .count();
     dbg!(pt1_answer);
 
-    // part 2
-    let mut fresh_ranges = fresh_ranges;
-    // merge the ranges
-    fresh_ranges.sort_by_key(|range| *range.start());
-    let fresh_ranges = {
-        let mut merged = Vec::new();
-        for range in fresh_ranges {
-            match merged.last_mut() {
-                None => merged.push(range),
-                Some(prevrange) => {
-                    if prevrange.end() >= range.start() {
-                        *prevrange = min(*prevrange.start(), *range.start())
-                            ..=max(*prevrange.end(), *range.end());
-                    } else {
-                        merged.push(range);
-                    }
+    let pt2_answer = fresh_ranges.covered_len();
+    dbg!(pt2_answer);
+}
+
+mod range_set {
+    use std::{
+        cmp::{max, min},
+        fmt::Display,
+        ops::RangeInclusive,
+    };
+
+    /// values which have a next value (or don't, at their maximum), so that we can tell when
+    /// ranges are adjacent
+    pub trait Discrete: Ord + Copy {
+        fn checked_next(self) -> Option<Self>;
+    }
+
+    impl Discrete for u64 {
+        fn checked_next(self) -> Option<Self> {
+            self.checked_add(1)
+        }
+    }
+
+    /// a set of values, stored as a sorted list of the disjoint ranges which make it up
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RangeSet<T> {
+        ranges: Vec<RangeInclusive<T>>,
+        /// whether ranges which touch without overlapping are merged together
+        merge_adjacent: bool,
+    }
+
+    impl<T> Default for RangeSet<T> {
+        fn default() -> Self {
+            Self {
+                ranges: Vec::new(),
+                merge_adjacent: false,
+            }
+        }
+    }
+
+    impl<T: Discrete> RangeSet<T> {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// when set, ranges like `3..=5` and `6..=8` are stored as one `3..=8` range. this doesn't
+        /// change which values are in the set, only how it's split up into ranges.
+        pub fn with_adjacent_merging(self, merge_adjacent: bool) -> Self {
+            let mut set = Self {
+                ranges: Vec::with_capacity(self.ranges.len()),
+                merge_adjacent,
+            };
+            set.extend(self.ranges);
+            set
+        }
+
+        /// a set with the same settings as this one, holding the given ranges
+        fn with_ranges(&self, ranges: impl IntoIterator<Item = RangeInclusive<T>>) -> Self {
+            let mut set = Self {
+                ranges: Vec::new(),
+                merge_adjacent: self.merge_adjacent,
+            };
+            set.extend(ranges);
+            set
+        }
+
+        /// adds every value in `range`, merging it with any ranges it overlaps
+        pub fn insert(&mut self, range: RangeInclusive<T>) {
+            if range.is_empty() {
+                return;
+            }
+            let (lo, hi) = (*range.start(), *range.end());
+            let touches = |a: T, b: T| self.merge_adjacent && a.checked_next() == Some(b);
+            // ranges[first..last] are all the ones which overlap (or touch) the new range
+            let first = self
+                .ranges
+                .partition_point(|r| *r.end() < lo && !touches(*r.end(), lo));
+            let last = self
+                .ranges
+                .partition_point(|r| *r.start() <= hi || touches(hi, *r.start()));
+            let merged = match first < last {
+                true => {
+                    min(lo, *self.ranges[first].start())..=max(hi, *self.ranges[last - 1].end())
+                }
+                false => range,
+            };
+            self.ranges.splice(first..last, [merged]);
+        }
+
+        /// the range in the set which `value` falls within, if any
+        pub fn range_containing(&self, value: &T) -> Option<&RangeInclusive<T>> {
+            let idx = self.ranges.partition_point(|r| r.end() < value);
+            self.ranges.get(idx).filter(|r| r.contains(value))
+        }
+
+        pub fn contains(&self, value: &T) -> bool {
+            self.range_containing(value).is_some()
+        }
+
+        /// the disjoint ranges making up the set, in ascending order
+        pub fn iter(&self) -> std::slice::Iter<'_, RangeInclusive<T>> {
+            self.ranges.iter()
+        }
+    }
+
+    impl RangeSet<u64> {
+        /// the number of values in the set. this can be anything up to 2^64, so doesn't fit in a
+        /// `u64`.
+        pub fn covered_len(&self) -> u128 {
+            self.ranges
+                .iter()
+                .map(|range| (range.end() - range.start()) as u128 + 1)
+                .sum()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.ranges.is_empty()
+        }
+
+        /// the smallest range containing the whole set
+        pub fn bounds(&self) -> Option<RangeInclusive<u64>> {
+            Some(*self.ranges.first()?.start()..=*self.ranges.last()?.end())
+        }
+
+        pub fn union(&self, other: &Self) -> Self {
+            self.with_ranges(self.iter().chain(other.iter()).cloned())
+        }
+
+        pub fn intersection(&self, other: &Self) -> Self {
+            // both lists are sorted, so walk along them together, always stepping past whichever
+            //  range ends first, since it can't overlap anything further along the other list
+            let (mut a, mut b) = (
+                self.ranges.iter().peekable(),
+                other.ranges.iter().peekable(),
+            );
+            let mut ranges = Vec::new();
+            while let (Some(ra), Some(rb)) = (a.peek(), b.peek()) {
+                let (lo, hi) = (max(*ra.start(), *rb.start()), min(*ra.end(), *rb.end()));
+                if lo <= hi {
+                    ranges.push(lo..=hi);
                 }
+                match ra.end() < rb.end() {
+                    true => a.next(),
+                    false => b.next(),
+                };
             }
+            self.with_ranges(ranges)
         }
-        merged
-    };
-    let pt2_answer: u64 = fresh_ranges
-        .iter()
-        .map(|range| range.end() - range.start() + 1)
-        .sum();
-    dbg!(pt2_answer);
+
+        /// every value within `bounds` which isn't in the set
+        pub fn complement_within(&self, bounds: RangeInclusive<u64>) -> Self {
+            let mut ranges = Vec::new();
+            // start of the next gap, or `None` if the last range ran right up to `u64::MAX`
+            let mut gap_start = Some(*bounds.start());
+            for range in &self.ranges {
+                let Some(lo) = gap_start.filter(|lo| lo <= bounds.end()) else {
+                    break;
+                };
+                if *range.start() > lo {
+                    ranges.push(lo..=min(*range.start() - 1, *bounds.end()));
+                }
+                gap_start = range.end().checked_add(1).map(|next| max(lo, next));
+            }
+            if let Some(lo) = gap_start.filter(|lo| lo <= bounds.end()) {
+                ranges.push(lo..=*bounds.end());
+            }
+            self.with_ranges(ranges)
+        }
+
+        /// values in `self` which aren't in `other`
+        pub fn difference(&self, other: &Self) -> Self {
+            match self.bounds() {
+                Some(bounds) => self.intersection(&other.complement_within(bounds)),
+                None => Self::new(),
+            }
+        }
+    }
+
+    /// one `lo-hi` range per line, in the same format as the puzzle input
+    impl<T: Display> Display for RangeSet<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            for (idx, range) in self.ranges.iter().enumerate() {
+                if idx > 0 {
+                    writeln!(f)?;
+                }
+                write!(f, "{}-{}", range.start(), range.end())?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<T: Discrete> Extend<RangeInclusive<T>> for RangeSet<T> {
+        fn extend<I: IntoIterator<Item = RangeInclusive<T>>>(&mut self, iter: I) {
+            for range in iter {
+                self.insert(range);
+            }
+        }
+    }
+
+    impl<T: Discrete> FromIterator<RangeInclusive<T>> for RangeSet<T> {
+        fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+            let mut set = Self::new();
+            set.extend(iter);
+            set
+        }
+    }
+
+    impl<T: Discrete> From<Vec<RangeInclusive<T>>> for RangeSet<T> {
+        fn from(ranges: Vec<RangeInclusive<T>>) -> Self {
+            ranges.into_iter().collect()
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a RangeSet<T> {
+        type Item = &'a RangeInclusive<T>;
+        type IntoIter = std::slice::Iter<'a, RangeInclusive<T>>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.ranges.iter()
+        }
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(vec![3..=5, 10..=14, 16..=20, 12..=18], vec![3..=5, 10..=20])]
+#[case::disjoint_out_of_order(vec![10..=12, 1..=2, 5..=6], vec![1..=2, 5..=6, 10..=12])]
+#[case::contained(vec![1..=10, 3..=4], vec![1..=10])]
+#[case::containing(vec![3..=4, 6..=7, 1..=10], vec![1..=10])]
+#[case::bridging(vec![1..=3, 7..=9, 3..=7], vec![1..=9])]
+#[case::touching_endpoints(vec![1..=3, 3..=5], vec![1..=5])]
+#[case::adjacent_kept_apart(vec![1..=3, 4..=5], vec![1..=3, 4..=5])]
+#[case::empty_range_ignored(vec![1..=3, RangeInclusive::new(9, 8)], vec![1..=3])]
+#[case::single_values(vec![5..=5, 5..=5, 6..=6], vec![5..=5, 6..=6])]
+fn test_range_set_insert(
+    #[case] ranges: Vec<RangeInclusive<u64>>,
+    #[case] expected: Vec<RangeInclusive<u64>>,
+) {
+    let set = RangeSet::from(ranges);
+    assert_eq!(expected, set.iter().cloned().collect::<Vec<_>>());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(vec![3..=5, 10..=14, 16..=20, 12..=18], vec![3..=5, 10..=20])]
+#[case::adjacent(vec![3..=5, 6..=8], vec![3..=8])]
+#[case::adjacent_on_both_sides(vec![1..=2, 6..=8, 3..=5], vec![1..=8])]
+#[case::gap_of_one(vec![3..=5, 7..=8], vec![3..=5, 7..=8])]
+#[case::at_max(vec![u64::MAX..=u64::MAX, 0..=u64::MAX - 1], vec![0..=u64::MAX])]
+fn test_range_set_merge_adjacent(
+    #[case] ranges: Vec<RangeInclusive<u64>>,
+    #[case] expected: Vec<RangeInclusive<u64>>,
+) {
+    let set = RangeSet::from(ranges).with_adjacent_merging(true);
+    assert_eq!(expected, set.iter().cloned().collect::<Vec<_>>());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::everything(vec![0..=u64::MAX], 1 << 64)]
+#[case::many_large(vec![0..=u64::MAX / 2, u64::MAX / 2 + 2..=u64::MAX], u64::MAX as u128)]
+fn test_range_set_covered_len_past_u64(
+    #[case] ranges: Vec<RangeInclusive<u64>>,
+    #[case] expected: u128,
+) {
+    assert_eq!(expected, RangeSet::from(ranges).covered_len());
+}
+
+/// property tests against a bitset of a small domain of IDs. `base` shifts the domain around, so
+/// that it can sit right up against `u64::MAX`.
+#[cfg(test)]
+mod proptests {
+    use super::RangeSet;
+    use proptest::prelude::*;
+    use std::ops::RangeInclusive;
+
+    const DOMAIN: u64 = 96;
+
+    fn base() -> impl Strategy<Value = u64> {
+        prop_oneof![Just(0), Just(u64::MAX - (DOMAIN - 1)), 0..u64::MAX - DOMAIN]
+    }
+
+    fn ranges(base: u64) -> impl Strategy<Value = Vec<RangeInclusive<u64>>> {
+        prop::collection::vec(
+            (0..DOMAIN, 0..DOMAIN / 4)
+                .prop_map(move |(lo, len)| base + lo..=base + (lo + len).min(DOMAIN - 1)),
+            0..8,
+        )
+    }
+
+    fn bitset(base: u64, ranges: &[RangeInclusive<u64>]) -> Vec<bool> {
+        let mut bits = vec![false; DOMAIN as usize];
+        for range in ranges {
+            bits[(range.start() - base) as usize..=(range.end() - base) as usize].fill(true);
+        }
+        bits
+    }
+
+    fn to_bitset(base: u64, set: &RangeSet<u64>) -> Vec<bool> {
+        (0..DOMAIN).map(|id| set.contains(&(base + id))).collect()
+    }
+
+    /// the ranges are sorted, and neither overlap nor (if merging) touch
+    fn assert_normalised(set: &RangeSet<u64>, merge_adjacent: bool) {
+        for (a, b) in set.iter().zip(set.iter().skip(1)) {
+            assert!(
+                a.end() < b.start(),
+                "{a:?} and {b:?} out of order or overlapping"
+            );
+            if merge_adjacent {
+                assert!(a.end() + 1 < *b.start(), "{a:?} and {b:?} not merged");
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn matches_bitset(
+            (base, ranges) in base().prop_flat_map(|base| (Just(base), ranges(base))),
+            merge_adjacent: bool,
+        ) {
+            let bits = bitset(base, &ranges);
+            let set = RangeSet::from(ranges).with_adjacent_merging(merge_adjacent);
+            assert_normalised(&set, merge_adjacent);
+            prop_assert_eq!(&bits, &to_bitset(base, &set));
+            prop_assert_eq!(bits.iter().filter(|&&b| b).count() as u128, set.covered_len());
+        }
+
+        #[test]
+        fn set_algebra_matches_bitset(
+            (base, a, b) in base().prop_flat_map(|base| (Just(base), ranges(base), ranges(base))),
+            merge_adjacent: bool,
+        ) {
+            let (bits_a, bits_b) = (bitset(base, &a), bitset(base, &b));
+            let a = RangeSet::from(a).with_adjacent_merging(merge_adjacent);
+            let b = RangeSet::from(b).with_adjacent_merging(merge_adjacent);
+            let zip = |f: fn(bool, bool) -> bool| {
+                bits_a.iter().zip(&bits_b).map(|(&x, &y)| f(x, y)).collect::<Vec<_>>()
+            };
+
+            for (result, expected) in [
+                (a.union(&b), zip(|x, y| x || y)),
+                (a.intersection(&b), zip(|x, y| x && y)),
+                (a.difference(&b), zip(|x, y| x && !y)),
+                (a.complement_within(base..=base + (DOMAIN - 1)), zip(|x, _| !x)),
+            ] {
+                assert_normalised(&result, merge_adjacent);
+                prop_assert_eq!(expected, to_bitset(base, &result));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::disjoint(vec![1..=3], vec![5..=6], vec![1..=3, 5..=6], vec![], vec![1..=3])]
+#[case::overlapping(vec![1..=5], vec![4..=8], vec![1..=8], vec![4..=5], vec![1..=3])]
+#[case::contained(vec![1..=10], vec![3..=4, 6..=7], vec![1..=10], vec![3..=4, 6..=7], vec![1..=2, 5..=5, 8..=10])]
+#[case::identical(vec![2..=4, 8..=9], vec![2..=4, 8..=9], vec![2..=4, 8..=9], vec![2..=4, 8..=9], vec![])]
+#[case::shared_endpoint(vec![1..=3], vec![3..=5], vec![1..=5], vec![3..=3], vec![1..=2])]
+#[case::empty(vec![1..=3], vec![], vec![1..=3], vec![], vec![1..=3])]
+#[case::extremes(vec![0..=u64::MAX], vec![0..=0, u64::MAX..=u64::MAX], vec![0..=u64::MAX], vec![0..=0, u64::MAX..=u64::MAX], vec![1..=u64::MAX - 1])]
+fn test_range_set_algebra(
+    #[case] a: Vec<RangeInclusive<u64>>,
+    #[case] b: Vec<RangeInclusive<u64>>,
+    #[case] union: Vec<RangeInclusive<u64>>,
+    #[case] intersection: Vec<RangeInclusive<u64>>,
+    #[case] difference: Vec<RangeInclusive<u64>>,
+) {
+    let (a, b) = (RangeSet::from(a), RangeSet::from(b));
+    assert_eq!(RangeSet::from(union), a.union(&b));
+    assert_eq!(RangeSet::from(intersection.clone()), a.intersection(&b));
+    assert_eq!(RangeSet::from(intersection), b.intersection(&a));
+    assert_eq!(RangeSet::from(difference), a.difference(&b));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(vec![3..=5, 10..=14, 16..=20, 12..=18], 3..=20, vec![6..=9])]
+#[case::wider_bounds(vec![3..=5, 10..=14], 0..=20, vec![0..=2, 6..=9, 15..=20])]
+#[case::narrower_bounds(vec![3..=5, 10..=14], 4..=11, vec![6..=9])]
+#[case::bounds_inside_range(vec![3..=15], 5..=10, vec![])]
+#[case::empty_set(vec![], 5..=10, vec![5..=10])]
+#[case::up_to_max(vec![0..=u64::MAX - 1], 0..=u64::MAX, vec![u64::MAX..=u64::MAX])]
+#[case::range_ends_at_max(vec![5..=u64::MAX], 0..=u64::MAX, vec![0..=4])]
+fn test_range_set_complement(
+    #[case] set: Vec<RangeInclusive<u64>>,
+    #[case] bounds: RangeInclusive<u64>,
+    #[case] expected: Vec<RangeInclusive<u64>>,
+) {
+    assert_eq!(
+        RangeSet::from(expected),
+        RangeSet::from(set).complement_within(bounds)
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::ranges_only("3-5\n10-14\n", vec![3..=5, 10..=14])]
+#[case::no_trailing_newline("3-5\n10-14", vec![3..=5, 10..=14])]
+#[case::full_input("3-5\n10-14\n\n1\n5\n", vec![3..=5, 10..=14])]
+fn test_parse_fresh_ranges(#[case] input: &str, #[case] expected: Vec<RangeInclusive<u64>>) {
+    assert_eq!(expected, parse::fresh_ranges.parse(input).unwrap());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::fresh(5, "5 fresh 3-5")]
+#[case::fresh_in_merged_range(17, "17 fresh 10-20")]
+#[case::spoiled(8, "8 spoiled")]
+#[case::past_the_end(u64::MAX, "18446744073709551615 spoiled")]
+fn test_query_result(#[case] id: u64, #[case] expected: &str) {
+    let fresh_ranges = RangeSet::from(vec![3..=5, 10..=14, 16..=20, 12..=18]);
+    assert_eq!(expected, query_result(&fresh_ranges, id));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::id("17", Some(17))]
+#[case::max("18446744073709551615", Some(u64::MAX))]
+#[case::too_big("18446744073709551616", None)]
+#[case::range("3-5", None)]
+#[case::words("17 and more", None)]
+fn test_parse_query_id(#[case] input: &str, #[case] expected: Option<u64>) {
+    assert_eq!(expected, parse::query_id.parse(input).ok());
+}
+
+#[cfg(test)]
+#[test]
+fn test_range_set_display_round_trips() {
+    let set = RangeSet::from(vec![3..=5, 10..=14, 16..=20, 12..=18, 0..=u64::MAX - 7]);
+    let parsed = parse::fresh_ranges.parse(set.to_string().as_str()).unwrap();
+    assert_eq!(set, RangeSet::from(parsed));
 }
 
 mod parse {
     use std::ops::RangeInclusive;
 
+    use common::parse::{blank_line_sections, lines, range_inclusive};
     use winnow::{
         ModalResult, Parser,
         ascii::{dec_uint, line_ending},
-        combinator::{alt, eof, repeat, separated_pair, terminated},
+        combinator::{alt, eof, preceded, terminated},
+        token::rest,
     };
 
     fn id(i: &mut &str) -> ModalResult<u64> {
         dec_uint.parse_next(i)
     }
 
-    fn range(i: &mut &str) -> ModalResult<RangeInclusive<u64>> {
-        separated_pair(id, '-', id)
-            .map(|(lo, hi)| lo..=hi)
-            .parse_next(i)
+    /// just the fresh ranges from the start of an input, ignoring the IDs after them if there are
+    /// any
+    pub fn fresh_ranges(i: &mut &str) -> ModalResult<Vec<RangeInclusive<u64>>> {
+        terminated(
+            lines(range_inclusive()),
+            alt((eof, preceded(line_ending, rest))),
+        )
+        .parse_next(i)
+    }
+
+    /// a single ID on its own, as given to query mode
+    pub fn query_id(i: &mut &str) -> ModalResult<u64> {
+        terminated(id, eof).parse_next(i)
     }
 
     pub fn full_input(i: &mut &str) -> ModalResult<(Vec<RangeInclusive<u64>>, Vec<u64>)> {
-        separated_pair(
-            repeat(0.., terminated(range, line_ending)),
-            line_ending,
-            repeat(0.., terminated(id, alt((line_ending, eof)))),
-        )
-        .parse_next(i)
+        blank_line_sections(range_inclusive(), id).parse_next(i)
     }
 }
@@ -1,10 +1,54 @@
 #![allow(unused)]
 
+use std::{fmt, path::PathBuf};
+
+use clap::Parser as _;
+use itertools::Itertools as _;
+#[cfg(test)]
+use rstest::rstest;
 use tap::Pipe as _;
 use winnow::Parser as _;
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Width {
+    U64,
+    U128,
+    /// arbitrary precision, which can't overflow
+    #[cfg(feature = "bigint")]
+    Big,
+}
+
+#[derive(clap::Parser)]
+struct Args {
+    input_files: Vec<PathBuf>,
+    /// the type to work problems out in. the numbers themselves still have to fit in a `u64`.
+    #[arg(long, value_enum, default_value_t = Width::U64)]
+    width: Width,
+    /// skip columns which some rows don't have numbers for (or which have no operator), rather
+    /// than rejecting the worksheet. for part 2, skips problems which can't be read instead
+    #[arg(long)]
+    lenient: bool,
+    /// print each problem, its answer and the running total, as well as the final answers
+    #[arg(long)]
+    explain: bool,
+    /// number of worker threads to use (defaults to one per cpu)
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
 fn main() {
-    let input_files = std::env::args().skip(1).collect::<Vec<_>>().pipe(|files| {
+    let args = Args::parse();
+
+    #[cfg(feature = "parallel")]
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("building thread pool failed");
+    }
+
+    let input_files = args.input_files.pipe(|files| {
         if files.is_empty() {
             vec!["sample.txt".into(), "input.txt".into()]
         } else {
@@ -13,80 +57,776 @@ fn main() {
     });
 
     for input_file in input_files {
-        println!("{input_file}");
+        println!("{}", input_file.display());
 
         let txt = std::fs::read_to_string(input_file).expect("reading input file failed");
 
-        let worksheet = parse::worksheet.parse(txt.as_str()).unwrap();
-
-        let part1_solution: u64 = {
-            let (numrows, oprow) = worksheet;
-            oprow
-                .iter()
-                .enumerate()
-                .map(|(idx, op)| {
-                    numrows
-                        .iter()
-                        .map(|row| row.get(idx).unwrap())
-                        .fold(op.fold_start(), |acc, cur| op.op(acc, *cur))
-                })
-                .sum()
+        let worksheet = match args.lenient {
+            true => parse::lenient_worksheet(&txt).map(|(worksheet, warnings)| {
+                for warning in warnings {
+                    eprintln!("warning: {warning}; skipping the incomplete columns");
+                }
+                worksheet
+            }),
+            false => parse::worksheet(&txt),
         };
-        dbg!(part1_solution);
+        let by_row = worksheet.map(|(numrows, oprow)| problems_by_row(&numrows, &oprow));
+        let by_column = match args.lenient {
+            true => parse::lenient_vertical_problems(&txt).map(|(problems, warnings)| {
+                for warning in warnings {
+                    eprintln!("warning: {warning}; skipping that problem in part 2");
+                }
+                problems
+            }),
+            false => parse::vertical_problems(&txt),
+        };
+        match args.width {
+            Width::U64 => print_solutions::<u64>(by_row, by_column, args.explain),
+            Width::U128 => print_solutions::<u128>(by_row, by_column, args.explain),
+            #[cfg(feature = "bigint")]
+            Width::Big => print_solutions::<num_bigint::BigUint>(by_row, by_column, args.explain),
+        }
+    }
+}
+
+/// solves both readings of the worksheet, reporting once for each part why it couldn't be
+/// solved, if it couldn't
+fn print_solutions<A: Accumulator + fmt::Debug>(
+    by_row: Result<Vec<Problem>, parse::Error>,
+    by_column: Result<Vec<Problem>, parse::Error>,
+    explain: bool,
+) {
+    let solve = |part: u8, problems: Result<Vec<Problem>, parse::Error>| {
+        let problems = problems.map_err(|err| format!("invalid worksheet: {err}"))?;
+        match explain {
+            true => {
+                // the totals go to stderr, so head each part's explanation to tell them apart
+                println!("part {part}:");
+                solve_explained::<A>(&problems, &mut std::io::stdout().lock())
+            }
+            false => solve::<A>(&problems),
+        }
+        .map_err(|err| err.to_string())
+    };
+    match solve(1, by_row) {
+        Ok(part1_solution) => {
+            dbg!(part1_solution);
+        }
+        Err(err) => eprintln!("part 1 failed: {err}"),
+    }
+    match solve(2, by_column) {
+        Ok(part2_solution) => {
+            dbg!(part2_solution);
+        }
+        Err(err) => eprintln!("part 2 failed: {err}"),
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EvalError {
+    /// working out the problem in this column overflowed, or went below zero. columns are
+    /// [`Problem::column`]s.
+    Overflow { column: usize },
+    /// the problem in this column divides by zero
+    DivideByZero { column: usize },
+    /// every problem could be worked out, but adding up their answers overflowed
+    TotalOverflow,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Overflow { column } => write!(f, "overflow in column {column}"),
+            EvalError::DivideByZero { column } => write!(f, "division by zero in column {column}"),
+            EvalError::TotalOverflow => write!(f, "overflow adding up the columns"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// a number type which problems can be worked out in
+trait Accumulator: Clone + Ord + fmt::Display + Send + Sync {
+    fn from_num(n: u64) -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    /// `None` if the result would be negative
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    /// rounds down. `None` when dividing by zero.
+    fn checked_div(&self, other: &Self) -> Option<Self>;
+}
+
+impl Accumulator for u64 {
+    fn from_num(n: u64) -> Self {
+        n
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        u64::checked_sub(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        u64::checked_mul(*self, *other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        u64::checked_div(*self, *other)
+    }
+}
+
+impl Accumulator for u128 {
+    fn from_num(n: u64) -> Self {
+        n.into()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        u128::checked_sub(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        u128::checked_mul(*self, *other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        u128::checked_div(*self, *other)
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl Accumulator for num_bigint::BigUint {
+    fn from_num(n: u64) -> Self {
+        n.into()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        (self >= other).then(|| self - other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        (*other != Self::from_num(0)).then(|| self / other)
+    }
+}
+
+/// one problem from the worksheet: an operation, and the numbers it combines, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Problem {
+    op: Op,
+    nums: Vec<u64>,
+    /// where the problem is in the worksheet, for errors, 1-based like the rows: which column of
+    /// numbers in the row-wise reading, or the character column it starts at in the column-wise
+    /// one
+    column: usize,
+}
+
+impl Problem {
+    fn solve<A: Accumulator>(&self) -> Result<A, EvalError> {
+        let column = self.column;
+        let mut nums = self.nums.iter().map(|num| A::from_num(*num));
+        let start = match self.op.fold_start() {
+            Some(identity) => identity,
+            None => nums.next().expect("problems have at least one number"),
+        };
+        nums.try_fold(start, |acc, cur| {
+            self.op.op(&acc, &cur).map_err(|err| match err {
+                OpError::Overflow => EvalError::Overflow { column },
+                OpError::DivideByZero => EvalError::DivideByZero { column },
+            })
+        })
+    }
+}
+
+/// written out like `123 * 45 * 6`
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sep = format!(" {} ", self.op);
+        write!(f, "{}", self.nums.iter().join(&sep))
+    }
+}
+
+/// the row-wise reading, where each column of numbers is a problem
+fn problems_by_row(numrows: &[Vec<u64>], oprow: &[Op]) -> Vec<Problem> {
+    oprow
+        .iter()
+        .enumerate()
+        .map(|(idx, op)| Problem {
+            op: *op,
+            nums: numrows.iter().map(|row| row[idx]).collect(),
+            column: idx + 1,
+        })
+        .collect()
+}
+
+/// the sum of the problems' answers
+fn solve<A: Accumulator>(problems: &[Problem]) -> Result<A, EvalError> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        let add_totals = |a: A, b: A| a.checked_add(&b).ok_or(EvalError::TotalOverflow);
+        problems
+            .par_iter()
+            .map(Problem::solve)
+            .try_reduce(|| A::from_num(0), add_totals)
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        solve_sequential(problems)
+    }
+}
+
+/// [`solve`] a problem at a time, as it's worked out without the `parallel` feature
+#[cfg(any(test, not(feature = "parallel")))]
+fn solve_sequential<A: Accumulator>(problems: &[Problem]) -> Result<A, EvalError> {
+    problems.iter().try_fold(A::from_num(0), |total, problem| {
+        let answer = problem.solve()?;
+        total.checked_add(&answer).ok_or(EvalError::TotalOverflow)
+    })
+}
+
+/// like [`solve`], but writing out each problem as it goes, along with the running total. this is
+/// always done in order, one problem at a time, even with the `parallel` feature.
+fn solve_explained<A: Accumulator>(
+    problems: &[Problem],
+    out: &mut impl std::io::Write,
+) -> Result<A, EvalError> {
+    let mut total = A::from_num(0);
+    for problem in problems {
+        let answer: A = problem.solve()?;
+        total = total.checked_add(&answer).ok_or(EvalError::TotalOverflow)?;
+        writeln!(out, "{problem} = {answer} (running total {total})")
+            .expect("writing explanation failed");
+    }
+    Ok(total)
+}
+
+/// the operation for a problem. the numbers are combined in the order they're read (from the top
+/// row down, in the row-wise reading), so a column of `10`, `3`, `2` under `-` is `(10 - 3) - 2`,
+/// and under `|` is `(10 | 3) | 2 = 1032`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Op {
     Add,
+    Sub,
     Mul,
+    /// integer division, rounding down
+    Div,
+    Max,
+    Min,
+    /// concatenating the digits, so `12 | 34 = 1234`
+    Concat,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Max => "max",
+            Op::Min => "min",
+            Op::Concat => "|",
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpError {
+    Overflow,
+    DivideByZero,
 }
 
 impl Op {
-    fn fold_start(&self) -> u64 {
+    /// the identity of the operation, in whichever type we're working in. `-` and `/` don't have
+    /// one, so they start from the top number instead.
+    fn fold_start<A: Accumulator>(&self) -> Option<A> {
         match self {
-            Op::Add => 0,
-            Op::Mul => 1,
+            Op::Add | Op::Max | Op::Concat => Some(A::from_num(0)),
+            Op::Mul => Some(A::from_num(1)),
+            // every number in the worksheet fits in a u64, so this is as big as they get
+            Op::Min => Some(A::from_num(u64::MAX)),
+            Op::Sub | Op::Div => None,
         }
     }
 
-    fn op(&self, a: u64, b: u64) -> u64 {
+    fn op<A: Accumulator>(&self, a: &A, b: &A) -> Result<A, OpError> {
         match self {
-            Op::Add => a + b,
-            Op::Mul => a * b,
+            Op::Add => a.checked_add(b).ok_or(OpError::Overflow),
+            Op::Sub => a.checked_sub(b).ok_or(OpError::Overflow),
+            Op::Mul => a.checked_mul(b).ok_or(OpError::Overflow),
+            Op::Div => a.checked_div(b).ok_or(OpError::DivideByZero),
+            Op::Max => Ok(a.max(b).clone()),
+            Op::Min => Ok(a.min(b).clone()),
+            Op::Concat => {
+                let ten = A::from_num(10);
+                (0..b.to_string().len())
+                    .try_fold(a.clone(), |shifted, _| shifted.checked_mul(&ten))
+                    .and_then(|shifted| shifted.checked_add(b))
+                    .ok_or(OpError::Overflow)
+            }
         }
     }
 }
 
 mod parse {
-    use super::Op;
+    use super::{Op, Problem};
+    use common::parse::aligned_columns;
+    use itertools::Itertools as _;
+    use std::fmt::Display;
     use tap::Pipe as _;
     use winnow::{
         ModalResult, Parser,
-        ascii::{dec_uint, line_ending, space0, space1},
-        combinator::{alt, delimited, opt, repeat, separated, terminated},
-        error::ParserError,
+        ascii::{dec_uint, line_ending},
+        combinator::{alt, opt, repeat, terminated},
     };
 
     fn op(input: &mut &str) -> ModalResult<Op> {
-        alt(('+'.value(Op::Add), '*'.value(Op::Mul))).parse_next(input)
+        alt((
+            '+'.value(Op::Add),
+            '-'.value(Op::Sub),
+            '*'.value(Op::Mul),
+            '/'.value(Op::Div),
+            "max".value(Op::Max),
+            "min".value(Op::Min),
+            '|'.value(Op::Concat),
+        ))
+        .parse_next(input)
     }
 
     fn num(input: &mut &str) -> ModalResult<u64> {
         dec_uint.parse_next(input)
     }
 
-    fn row<'a, O, E, P>(item: P) -> impl Parser<&'a str, Vec<O>, E>
-    where
-        E: ParserError<&'a str>,
-        P: Parser<&'a str, O, E>,
-    {
-        delimited(space0, separated(1.., item, space1), space0)
+    pub type Worksheet = (Vec<Vec<u64>>, Vec<Op>);
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Error {
+        Syntax(String),
+        /// a row of numbers with a different number of columns to the operator row
+        RaggedRow {
+            /// 1-based
+            row: usize,
+            expected: usize,
+            found: usize,
+        },
+    }
+
+    impl Error {
+        /// the first column the row and operator row disagree about, 1-based like the row
+        pub fn column(&self) -> Option<usize> {
+            match self {
+                Error::Syntax(_) => None,
+                Error::RaggedRow {
+                    expected, found, ..
+                } => Some(*expected.min(found) + 1),
+            }
+        }
     }
 
-    pub fn worksheet(input: &mut &str) -> ModalResult<(Vec<Vec<u64>>, Vec<Op>)> {
-        (repeat(2.., terminated(row(num), line_ending)), row(op))
+    impl Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Error::Syntax(msg) => write!(f, "{msg}"),
+                Error::RaggedRow {
+                    row,
+                    expected,
+                    found,
+                } => {
+                    let column = self.column().unwrap();
+                    let problem = match found < expected {
+                        true => "has no number",
+                        false => "has no operator",
+                    };
+                    write!(
+                        f,
+                        "row {row} has {found} numbers, but the operator row has {expected} \
+                         (column {column} {problem})"
+                    )
+                }
+            }
+        }
+    }
+
+    /// at least two rows of numbers, then a row of operators
+    fn rows(input: &mut &str) -> ModalResult<Worksheet> {
+        (
+            repeat(2.., terminated(aligned_columns(num), line_ending)),
+            aligned_columns(op),
+        )
             .pipe(|parser| terminated(parser, opt(line_ending)))
             .parse_next(input)
     }
+
+    /// every row which doesn't have one number per operator
+    fn ragged_rows(numrows: &[Vec<u64>], n_ops: usize) -> Vec<Error> {
+        numrows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.len() != n_ops)
+            .map(|(idx, row)| Error::RaggedRow {
+                row: idx + 1,
+                expected: n_ops,
+                found: row.len(),
+            })
+            .collect()
+    }
+
+    /// a worksheet where every row has a number for each operator
+    pub fn worksheet(input: &str) -> Result<Worksheet, Error> {
+        let (numrows, oprow) = rows
+            .parse(input)
+            .map_err(|err| Error::Syntax(err.to_string()))?;
+        match ragged_rows(&numrows, oprow.len()).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok((numrows, oprow)),
+        }
+    }
+
+    /// like [`worksheet`], but rather than rejecting ragged rows, drops any column which isn't
+    /// complete. as numbers are only separated by spaces, a short row is taken to be missing its
+    /// last columns. the ragged rows are returned, to be reported as warnings.
+    pub fn lenient_worksheet(input: &str) -> Result<(Worksheet, Vec<Error>), Error> {
+        let (mut numrows, mut oprow) = rows
+            .parse(input)
+            .map_err(|err| Error::Syntax(err.to_string()))?;
+        let warnings = ragged_rows(&numrows, oprow.len());
+
+        let complete_columns = numrows
+            .iter()
+            .map(Vec::len)
+            .chain([oprow.len()])
+            .min()
+            .unwrap();
+        for row in &mut numrows {
+            row.truncate(complete_columns);
+        }
+        oprow.truncate(complete_columns);
+        Ok(((numrows, oprow), warnings))
+    }
+
+    /// the column-wise reading, where each column of characters holds the digits of one number,
+    /// from the most significant at the top. problems are separated by columns of spaces, and
+    /// their operator sits under their leftmost column. as in the puzzle, each problem's numbers
+    /// are read from right to left.
+    pub fn vertical_problems(input: &str) -> Result<Vec<Problem>, Error> {
+        vertical_readings(input)?.into_iter().collect()
+    }
+
+    /// like [`vertical_problems`], but skips any problem which can't be read rather than
+    /// rejecting the worksheet. the reasons they couldn't be read are returned, to be reported as
+    /// warnings.
+    pub fn lenient_vertical_problems(input: &str) -> Result<(Vec<Problem>, Vec<Error>), Error> {
+        Ok(vertical_readings(input)?.into_iter().partition_result())
+    }
+
+    /// each block of non-blank columns, read as a problem
+    fn vertical_readings(input: &str) -> Result<Vec<Result<Problem, Error>>, Error> {
+        let lines: Vec<&[u8]> = input.lines().map(str::as_bytes).collect();
+        let Some((oprow, numrows)) = lines.split_last() else {
+            return Err(Error::Syntax("worksheet is empty".into()));
+        };
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let at = |line: &[u8], x: usize| line.get(x).copied().unwrap_or(b' ');
+        let blank = |x: usize| lines.iter().all(|line| at(line, x) == b' ');
+
+        // `start..end` is a block of columns, 0-based, though errors give them 1-based
+        let read = |start: usize, end: usize| {
+            let op_text = oprow.get(start..end.min(oprow.len())).unwrap_or_default();
+            let op = std::str::from_utf8(op_text)
+                .ok()
+                .and_then(|op_text| op.parse(op_text.trim()).ok())
+                .ok_or_else(|| Error::Syntax(format!("no operator under column {}", start + 1)))?;
+
+            let mut nums = Vec::new();
+            for col in (start..end).rev() {
+                let digits: Vec<u8> = numrows
+                    .iter()
+                    .map(|line| at(line, col))
+                    .filter(|&c| c != b' ')
+                    .collect();
+                if digits.is_empty() {
+                    continue;
+                }
+                let n = std::str::from_utf8(&digits)
+                    .ok()
+                    .and_then(|digits| num.parse(digits).ok())
+                    .ok_or_else(|| Error::Syntax(format!("column {} isn't a number", col + 1)))?;
+                nums.push(n);
+            }
+            if nums.is_empty() {
+                return Err(Error::Syntax(format!("no numbers in column {}", start + 1)));
+            }
+            Ok(Problem {
+                op,
+                nums,
+                column: start + 1,
+            })
+        };
+
+        let mut problems = Vec::new();
+        let mut x = 0;
+        while x < width {
+            if blank(x) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && !blank(x) {
+                x += 1;
+            }
+            problems.push(read(start, x));
+        }
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+const SAMPLE: &str = "\
+123 328  51 64 
+ 45 64  387 23 
+  6 98  215 314
+*   +   *   +  
+";
+
+#[cfg(test)]
+fn solve_part1<A: Accumulator>(input: &str) -> Result<A, EvalError> {
+    let (numrows, oprow) = parse::worksheet(input).unwrap();
+    solve(&problems_by_row(&numrows, &oprow))
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE, Ok(4277556))]
+#[case::u64_max("18446744073709551615\n1\n*", Ok(u64::MAX))]
+#[case::column_overflows("1 4294967296\n1 4294967296\n+ *", Err(EvalError::Overflow { column: 2 }))]
+#[case::total_overflows("18446744073709551615 1\n0 0\n+ +", Err(EvalError::TotalOverflow))]
+fn test_part1_u64(#[case] input: &str, #[case] expected: Result<u64, EvalError>) {
+    assert_eq!(expected, solve_part1::<u64>(input));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE, Ok(4277556))]
+#[case::column_overflows_u64("1 4294967296\n1 4294967296\n+ *", Ok(2 + (1 << 64)))]
+#[case::total_overflows_u64("18446744073709551615 1\n0 0\n+ +", Ok(1 << 64))]
+#[case::column_overflows(
+    "1 18446744073709551615\n1 18446744073709551615\n1 18446744073709551615\n+ *",
+    Err(EvalError::Overflow { column: 2 })
+)]
+fn test_part1_u128(#[case] input: &str, #[case] expected: Result<u128, EvalError>) {
+    assert_eq!(expected, solve_part1::<u128>(input));
+}
+
+#[cfg(all(test, feature = "bigint"))]
+#[test]
+fn test_part1_bigint() {
+    let input = "1 18446744073709551615\n1 18446744073709551615\n1 18446744073709551615\n+ *";
+    let expected = num_bigint::BigUint::from(u64::MAX).pow(3) + 3u32;
+    assert_eq!(Ok(expected), solve_part1(input));
+}
+
+#[cfg(test)]
+#[rstest]
+fn test_fold_start_is_identity(
+    #[values(Op::Add, Op::Mul, Op::Max, Op::Min, Op::Concat)] op: Op,
+    #[values(0, 1, 7, 10, 12345, u64::MAX)] n: u64,
+) {
+    assert_eq!(Ok(n), op.op(&op.fold_start().unwrap(), &n));
+    assert_eq!(
+        Ok(n as u128),
+        op.op(&op.fold_start().unwrap(), &(n as u128))
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::add("+", Ok(15))]
+#[case::mul("*", Ok(60))]
+#[case::max("max", Ok(10))]
+#[case::min("min", Ok(2))]
+// the rest aren't associative, so these check that they're worked out from the top row down
+#[case::sub("-", Ok(5))]
+#[case::div("/", Ok(1))]
+#[case::concat("|", Ok(1032))]
+fn test_op_column(#[case] op: &str, #[case] expected: Result<u64, EvalError>) {
+    let input = format!("10\n3\n2\n{op}\n");
+    assert_eq!(expected, solve_part1::<u64>(&input));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sub_below_zero("3 2\n5 3\n+ -", Err(EvalError::Overflow { column: 2 }))]
+#[case::div_by_zero("3 3\n5 0\n+ /", Err(EvalError::DivideByZero { column: 2 }))]
+#[case::zero_div("0\n5\n/", Ok(0))]
+#[case::concat_zero("10\n0\n|", Ok(100))]
+#[case::concat_overflows("4294967296\n4294967296\n|", Err(EvalError::Overflow { column: 1 }))]
+#[case::mixed("12 9 1 5\n34 3 2 7\n| / max min", Ok(1234 + 3 + 2 + 5))]
+fn test_extended_ops(#[case] input: &str, #[case] expected: Result<u64, EvalError>) {
+    assert_eq!(expected, solve_part1::<u64>(input));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::short_row("1 2 3\n4 5\n+ + +", 2, 3, 2, 3)]
+#[case::long_row("1 2 3\n4 5 6 7\n+ + +", 2, 3, 4, 4)]
+#[case::short_first_row("1\n4 5\n6 7\n+ +", 1, 2, 1, 2)]
+fn test_ragged_worksheet(
+    #[case] input: &str,
+    #[case] row: usize,
+    #[case] expected: usize,
+    #[case] found: usize,
+    #[case] column: usize,
+) {
+    let err = parse::worksheet(input).unwrap_err();
+    assert_eq!(
+        parse::Error::RaggedRow {
+            row,
+            expected,
+            found
+        },
+        err
+    );
+    assert_eq!(Some(column), err.column());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::short_row(
+    "1 2 3\n4 5\n+ + +",
+    "row 2 has 2 numbers, but the operator row has 3 (column 3 has no number)"
+)]
+#[case::long_row(
+    "1 2 3\n4 5 6 7\n+ + +",
+    "row 2 has 4 numbers, but the operator row has 3 (column 4 has no operator)"
+)]
+fn test_ragged_worksheet_message(#[case] input: &str, #[case] expected: &str) {
+    assert_eq!(expected, parse::worksheet(input).unwrap_err().to_string());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::complete(SAMPLE, 4, 0)]
+#[case::short_row("1 2 3\n4 5\n+ + +", 2, 1)]
+#[case::long_row("1 2 3\n4 5 6 7\n+ + +", 3, 1)]
+#[case::short_oprow("1 2 3\n4 5 6\n+ +", 2, 2)]
+#[case::several_short_rows("1 2 3\n4\n5 6\n+ + +", 1, 2)]
+fn test_lenient_worksheet(
+    #[case] input: &str,
+    #[case] expected_columns: usize,
+    #[case] expected_warnings: usize,
+) {
+    let ((numrows, oprow), warnings) = parse::lenient_worksheet(input).unwrap();
+    assert_eq!(expected_columns, oprow.len());
+    assert!(numrows.iter().all(|row| row.len() == expected_columns));
+    assert_eq!(expected_warnings, warnings.len());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE, 3263827)]
+#[case::ragged_lines("12\n3\n+", 13 + 2)]
+#[case::multi_char_op("123 45\n456 78\nmax +\n", 36 + 105)]
+#[case::right_to_left("12\n34\n-", 24 - 13)]
+fn test_part2(#[case] input: &str, #[case] expected: u64) {
+    let problems = parse::vertical_problems(input).unwrap();
+    assert_eq!(Ok(expected), solve(&problems));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::complete(SAMPLE, 4, 0)]
+#[case::no_operator("12 34\n56 78\n+    \n", 1, 1)]
+#[case::not_a_number("12 3x\n56 78\n+  * \n", 1, 1)]
+#[case::nothing_readable("1x\n2\n \n", 0, 1)]
+fn test_lenient_vertical_problems(
+    #[case] input: &str,
+    #[case] expected_problems: usize,
+    #[case] expected_warnings: usize,
+) {
+    let (problems, warnings) = parse::lenient_vertical_problems(input).unwrap();
+    assert_eq!(expected_problems, problems.len());
+    assert_eq!(expected_warnings, warnings.len());
+    assert_eq!(
+        expected_warnings > 0,
+        parse::vertical_problems(input).is_err()
+    );
+}
+
+#[cfg(all(test, feature = "parallel"))]
+#[rstest]
+#[case::by_row(parse::worksheet(SAMPLE).map(|(numrows, oprow)| problems_by_row(&numrows, &oprow)))]
+#[case::by_column(parse::vertical_problems(SAMPLE))]
+#[case::overflow(parse::worksheet("1 4294967296\n1 4294967296\n+ *").map(|(numrows, oprow)| problems_by_row(&numrows, &oprow)))]
+fn test_parallel_matches_sequential(#[case] problems: Result<Vec<Problem>, parse::Error>) {
+    let problems = problems.unwrap();
+    assert_eq!(solve_sequential::<u64>(&problems), solve::<u64>(&problems));
+}
+
+/// in the column-wise reading, errors give the character column each problem starts at
+#[cfg(test)]
+#[rstest]
+#[case::strict(parse::vertical_problems("1  2\n1 03\n+ / "), 3)]
+#[case::lenient_skips_a_problem(
+    parse::lenient_vertical_problems("9 1  2\n9 1 03\n  + / ").map(|(problems, _)| problems),
+    5
+)]
+fn test_vertical_error_column(
+    #[case] problems: Result<Vec<Problem>, parse::Error>,
+    #[case] column: usize,
+) {
+    assert_eq!(
+        Err(EvalError::DivideByZero { column }),
+        solve::<u64>(&problems.unwrap())
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_solve_explained() {
+    let (numrows, oprow) = parse::worksheet(SAMPLE).unwrap();
+    let mut out = Vec::new();
+    let total = solve_explained::<u64>(&problems_by_row(&numrows, &oprow), &mut out);
+    assert_eq!(Ok(4277556), total);
+    assert_eq!(
+        "\
+123 * 45 * 6 = 33210 (running total 33210)
+328 + 64 + 98 = 490 (running total 33700)
+51 * 387 * 215 = 4243455 (running total 4277155)
+64 + 23 + 314 = 401 (running total 4277556)
+",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::row_wise(
+    parse::worksheet(SAMPLE).map(|(numrows, oprow)| problems_by_row(&numrows, &oprow)),
+    vec!["123 * 45 * 6", "328 + 64 + 98", "51 * 387 * 215", "64 + 23 + 314"],
+)]
+#[case::column_wise(
+    parse::vertical_problems(SAMPLE),
+    vec!["356 * 24 * 1", "8 + 248 + 369", "175 * 581 * 32", "4 + 431 + 623"],
+)]
+fn test_problem_display(
+    #[case] problems: Result<Vec<Problem>, parse::Error>,
+    #[case] expected: Vec<&str>,
+) {
+    let rendered: Vec<_> = problems.unwrap().iter().map(Problem::to_string).collect();
+    assert_eq!(expected, rendered);
 }
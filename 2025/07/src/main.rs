@@ -1,12 +1,106 @@
 #![allow(unused)]
 
-use std::fmt::{Display, Write as _};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write as _},
+    path::PathBuf,
+};
 
+use clap::Parser as _;
+use common::grid::{Boundary, Dir, Grid, ParseError, Pos};
+use itertools::Itertools as _;
+#[cfg(test)]
+use rstest::rstest;
 use tap::Pipe as _;
-use winnow::Parser as _;
+
+#[derive(clap::Parser)]
+#[command(group(clap::ArgGroup::new("drawing").multiple(true)))]
+struct Args {
+    input_files: Vec<PathBuf>,
+    /// print the manifold with its beams drawn in
+    #[arg(long, group = "drawing")]
+    render: bool,
+    /// print the manifold as each row's beams are worked out
+    #[arg(long, group = "drawing")]
+    frames: bool,
+    /// (with --render or --frames) colour the manifold with ANSI escape codes
+    #[arg(long, requires = "drawing")]
+    colour: bool,
+    /// list which splitters are hit, and by how many timelines, and which are never reached
+    #[arg(long, value_enum)]
+    report: Option<ReportFormat>,
+    /// print the graph of which splitters feed beams to which others
+    #[arg(long, value_enum, conflicts_with_all = ["report", "render", "frames"])]
+    graph: Option<GraphFormat>,
+}
+
+impl Args {
+    /// whether stdout carries json or dot, which nothing else may be printed alongside
+    fn machine_readable(&self) -> bool {
+        self.graph.is_some() || matches!(self.report, Some(ReportFormat::Json))
+    }
+
+    /// the checks between arguments which clap can't express
+    fn validated(self) -> Result<Self, clap::Error> {
+        use clap::{CommandFactory as _, error::ErrorKind};
+        if !self.machine_readable() {
+            return Ok(self);
+        }
+        if self.render || self.frames {
+            return Err(Args::command().error(
+                ErrorKind::ArgumentConflict,
+                "--render and --frames can't be printed alongside --report json",
+            ));
+        }
+        if self.input_files.len() != 1 {
+            return Err(Args::command().error(
+                ErrorKind::WrongNumberOfValues,
+                "--report json and --graph take exactly one input file",
+            ));
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::default(&[], true)]
+#[case::text_report_and_render(&["a.txt", "b.txt", "--report", "text", "--render"], true)]
+#[case::json_report(&["a.txt", "--report", "json"], true)]
+#[case::graph(&["a.txt", "--graph", "dot"], true)]
+#[case::json_report_default_files(&["--report", "json"], false)]
+#[case::graph_two_files(&["a.txt", "b.txt", "--graph", "json"], false)]
+#[case::json_report_and_render(&["a.txt", "--report", "json", "--render"], false)]
+#[case::json_report_and_frames(&["a.txt", "--report", "json", "--frames"], false)]
+#[case::graph_and_report(&["a.txt", "--graph", "json", "--report", "json"], false)]
+#[case::graph_and_render(&["a.txt", "--graph", "dot", "--render"], false)]
+#[case::colour_render(&["--render", "--colour"], true)]
+#[case::colour_frames(&["--frames", "--colour"], true)]
+#[case::colour_render_and_frames(&["--render", "--frames", "--colour"], true)]
+#[case::colour_alone(&["--colour"], false)]
+fn test_args(#[case] args: &[&str], #[case] valid: bool) {
+    let parsed = Args::try_parse_from(["day07"].iter().chain(args)).and_then(Args::validated);
+    assert_eq!(valid, parsed.is_ok());
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum GraphFormat {
+    /// for graphviz
+    Dot,
+    /// as adjacency lists
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ReportFormat {
+    Text,
+    Json,
+}
 
 fn main() {
-    let input_files = std::env::args().skip(1).collect::<Vec<_>>().pipe(|files| {
+    let args = Args::parse().validated().unwrap_or_else(|err| err.exit());
+    let machine_readable = args.machine_readable();
+    let input_files = args.input_files.pipe(|files| {
         if files.is_empty() {
             vec!["sample.txt".into(), "input.txt".into()]
         } else {
@@ -14,113 +108,816 @@ fn main() {
         }
     });
 
+    // keep stdout parseable when it's carrying json or dot
     for input_file in input_files {
-        println!("{input_file}");
-        let txt = std::fs::read_to_string(input_file).expect("reading input file failed");
-        dbg!(part1(&txt));
-    }
-}
-
-fn part1(txt: &str) -> u64 {
-    let mut manifold = parse::tachyon_manifold.parse(txt).unwrap();
-
-    let mut n_splits = 0u64;
-
-    for row_idx in 0..manifold.rows.len() {
-        if row_idx == 0 {
-            continue;
+        match machine_readable {
+            true => eprintln!("{}", input_file.display()),
+            false => println!("{}", input_file.display()),
         }
-        let [prevrow, mut row] = manifold
-            .rows
-            .get_disjoint_mut([row_idx - 1, row_idx])
-            .unwrap();
-        for (cur, prev) in row.iter_mut().zip(prevrow.iter()) {
-            if matches!(prev, Kind::Beam | Kind::EntryPoint) {
-                match cur {
-                    cur @ Kind::Air => *cur = Kind::Beam,
-                    Kind::Splitter { hit } => {
-                        *hit = true;
-                        n_splits += 1;
-                    },
-                    _ => {}
-                }
+        let txt = std::fs::read_to_string(input_file).expect("reading input file failed");
+        let manifold = match parse::tachyon_manifold(&txt) {
+            Ok(manifold) => manifold,
+            Err(err) => {
+                eprintln!("invalid manifold: {err}");
+                continue;
+            }
+        };
+        let beams = manifold.simulate();
+        if args.frames {
+            for row in 0..=manifold.cells.rows() {
+                let diagram = manifold
+                    .diagram(&beams)
+                    .up_to_row(row)
+                    .coloured(args.colour);
+                println!("row {row}:\n{diagram}\n");
             }
         }
-        for idx in 0..row.len() {
-            if row[idx] != Kind::Air {
-                continue;
+        if args.render {
+            println!("{}", manifold.diagram(&beams).coloured(args.colour));
+        }
+        match args.report {
+            Some(ReportFormat::Text) => print!("{}", SplitterReport::new(&manifold, &beams)),
+            Some(ReportFormat::Json) => {
+                println!("{}", SplitterReport::new(&manifold, &beams).to_json())
             }
-            let left = (idx > 0).then(|| prevrow[idx - 1]);
-            let right = prevrow.get(idx + 1);
-            if matches!(left, Some(Kind::Splitter { hit: true }))
-                || matches!(right, Some(Kind::Splitter { hit: true }))
-            {
-                row[idx] = Kind::Beam;
+            None => {}
+        }
+        match args.graph {
+            Some(GraphFormat::Dot) => print!("{}", SplitterGraph::new(&manifold, &beams).to_dot()),
+            Some(GraphFormat::Json) => {
+                println!("{}", SplitterGraph::new(&manifold, &beams).to_json())
             }
+            None => {}
         }
+        dbg!(part1(&manifold, &beams));
+        dbg!(part2(&manifold, &beams));
+    }
+}
+
+/// the number of splitters which a beam reaches
+fn part1(manifold: &TachyonManifold, beams: &BeamLayer) -> u64 {
+    manifold
+        .cells
+        .iter()
+        .filter(|&(pos, &kind)| kind == Kind::Splitter && beams.has_beam(pos))
+        .count() as u64
+}
+
+/// the number of timelines a single particle ends up in, each splitter sending it both ways. a
+/// timeline is counted when its beam leaves the bottom of the manifold, or is caught by an
+/// absorber. timelines whose beam is lost aren't counted: those running into a wall, reaching a
+/// splitter or deflector diagonally, or sent diagonally off the side of the manifold.
+fn part2(manifold: &TachyonManifold, beams: &BeamLayer) -> u64 {
+    let absorbed: u64 = manifold
+        .cells
+        .iter()
+        .filter(|&(_, &kind)| kind == Kind::Absorber)
+        .map(|(pos, _)| beams.timelines[pos])
+        .sum();
+    exiting_timelines(manifold, beams) + absorbed
+}
+
+/// the number of timelines in which a beam leaves the bottom of the manifold
+fn exiting_timelines(manifold: &TachyonManifold, beams: &BeamLayer) -> u64 {
+    let Some(last_row) = manifold.cells.rows().checked_sub(1) else {
+        return 0;
+    };
+    manifold
+        .cells
+        .row(last_row)
+        .iter()
+        .zip(beams.timelines.row(last_row))
+        // a splitter on the bottom row still splits, its beams just leave the manifold
+        .map(|(kind, timelines)| {
+            timelines * kind.exits().iter().filter(|&&exit| exit).count() as u64
+        })
+        .sum()
+}
+
+/// the beams running through a manifold, worked out separately so that the manifold itself is
+/// left as it was parsed
+struct BeamLayer {
+    /// for each cell, the number of timelines in which a beam passes through it (or, for
+    /// splitters, arrives at it)
+    timelines: Grid<u64>,
+}
+
+impl BeamLayer {
+    fn has_beam(&self, pos: Pos) -> bool {
+        self.timelines[pos] > 0
     }
-    // println!("{manifold}");
-    n_splits
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Kind {
     Air,
-    Splitter { hit: bool },
+    Splitter,
     EntryPoint,
     Beam,
+    /// `/`, which sends beams from above down to its left
+    DeflectorLeft,
+    /// `\`, which sends beams from above down to its right
+    DeflectorRight,
+    /// stops any beam reaching it. the timelines it catches still count towards part 2.
+    Absorber,
+    /// blocks beams, which are lost, like ones going off the side of the manifold
+    Wall,
+}
+
+impl Kind {
+    /// the ways a beam leaves this cell, having reached it: down-left, straight down and
+    /// down-right
+    fn exits(self) -> [bool; 3] {
+        match self {
+            Kind::Air | Kind::EntryPoint | Kind::Beam => [false, true, false],
+            Kind::Splitter => [true, false, true],
+            Kind::DeflectorLeft => [true, false, false],
+            Kind::DeflectorRight => [false, false, true],
+            Kind::Absorber | Kind::Wall => [false, false, false],
+        }
+    }
+
+    /// whether a beam coming diagonally from a splitter or deflector above can reach this cell.
+    /// splitters and deflectors only take beams from straight above.
+    fn accepts_diagonal(self) -> bool {
+        !matches!(
+            self,
+            Kind::Splitter | Kind::DeflectorLeft | Kind::DeflectorRight | Kind::Wall
+        )
+    }
+
+    /// whether a beam goes straight through this cell, as through air
+    fn passes_through(self) -> bool {
+        self.exits() == [false, true, false]
+    }
 }
 
 impl Display for Kind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Kind::Air => f.write_char('.'),
-            Kind::Splitter { .. } => f.write_char('^'),
+            Kind::Splitter => f.write_char('^'),
             Kind::EntryPoint => f.write_char('S'),
             Kind::Beam => f.write_char('|'),
+            Kind::DeflectorLeft => f.write_char('/'),
+            Kind::DeflectorRight => f.write_char('\\'),
+            Kind::Absorber => f.write_char('@'),
+            Kind::Wall => f.write_char('#'),
         }
     }
 }
 
 struct TachyonManifold {
-    rows: Vec<Vec<Kind>>,
+    cells: Grid<Kind>,
 }
 
-impl Display for TachyonManifold {
+impl TachyonManifold {
+    /// the manifold with all of the beams drawn in, and no colour
+    fn diagram<'a>(&'a self, beams: &'a BeamLayer) -> Diagram<'a> {
+        Diagram {
+            manifold: self,
+            beams,
+            rows_done: self.cells.rows(),
+            colour: false,
+        }
+    }
+
+    /// sends beams down from each entry point, a row at a time. a beam carries straight down
+    /// until it reaches a splitter, which sends beams down-left and down-right of itself instead,
+    /// or a deflector, which sends it to just one side. beams already drawn in the input act as
+    /// extra sources, like entry points, unless a beam above feeds them.
+    fn simulate(&self) -> BeamLayer {
+        let (rows, cols) = self.cells.size();
+        let mut beams = BeamLayer {
+            timelines: Grid::new(rows, cols, 0),
+        };
+        for pos in self.cells.positions() {
+            let source = self.is_source(&beams, pos) as u64;
+            beams.timelines[pos] = source + self.incoming(&beams, pos);
+        }
+        beams
+    }
+
+    /// the timelines in which a beam reaches `pos` from the row above, once `beams` has been
+    /// worked out down to that row
+    fn incoming(&self, beams: &BeamLayer, pos: Pos) -> u64 {
+        // timelines from the cell in direction `dir`, if it sends beams out this way (an index
+        // into `Kind::exits`)
+        let from = |dir: Dir, exit: usize| {
+            self.cells
+                .step(pos, dir, Boundary::Bounded)
+                .filter(|&above| self.cells[above].exits()[exit])
+                .map_or(0, |above| beams.timelines[above])
+        };
+        let straight_down = from(Dir::N, 1);
+        match self.cells[pos] {
+            Kind::Wall => 0,
+            kind if !kind.accepts_diagonal() => straight_down,
+            _ => straight_down + from(Dir::NW, 2) + from(Dir::NE, 0),
+        }
+    }
+
+    /// whether a new beam starts at `pos`: at an entry point, or at a beam drawn in the input
+    /// which nothing above feeds. a drawn beam which is fed just carries the beams above it on,
+    /// so a manifold re-read from its own diagram gives the same answers.
+    fn is_source(&self, beams: &BeamLayer, pos: Pos) -> bool {
+        match self.cells[pos] {
+            Kind::EntryPoint => true,
+            Kind::Beam => self.incoming(beams, pos) == 0,
+            _ => false,
+        }
+    }
+}
+
+/// which splitters the beams reach. positions are `(row, col)`, counting from 0 at the top left.
+#[derive(Debug, PartialEq, Eq)]
+struct SplitterReport {
+    /// each splitter a beam reaches, with the number of timelines in which one does
+    hit: Vec<(Pos, u64)>,
+    unreached: Vec<Pos>,
+}
+
+impl SplitterReport {
+    fn new(manifold: &TachyonManifold, beams: &BeamLayer) -> Self {
+        let (hit, unreached): (Vec<_>, Vec<_>) = manifold
+            .cells
+            .iter()
+            .filter(|&(_, &kind)| kind == Kind::Splitter)
+            .map(|(pos, _)| (pos, beams.timelines[pos]))
+            .partition(|(_, timelines)| *timelines > 0);
+        Self {
+            hit,
+            unreached: unreached.into_iter().map(|(pos, _)| pos).collect(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let hit = self
+            .hit
+            .iter()
+            .map(|(Pos { row, col }, timelines)| {
+                format!(r#"{{"row":{row},"col":{col},"timelines":{timelines}}}"#)
+            })
+            .join(",");
+        let unreached = self
+            .unreached
+            .iter()
+            .map(|Pos { row, col }| format!(r#"{{"row":{row},"col":{col}}}"#))
+            .join(",");
+        format!(r#"{{"hit":[{hit}],"unreached":[{unreached}]}}"#)
+    }
+}
+
+impl Display for SplitterReport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (idx, row) in self.rows.iter().enumerate() {
-            if idx > 0 {
-                f.write_char('\n');
+        writeln!(f, "{} splitters hit (row, col: timelines):", self.hit.len())?;
+        for (Pos { row, col }, timelines) in &self.hit {
+            writeln!(f, "  {row}, {col}: {timelines}")?;
+        }
+        writeln!(
+            f,
+            "{} splitters unreached (row, col):",
+            self.unreached.len()
+        )?;
+        for Pos { row, col } in &self.unreached {
+            writeln!(f, "  {row}, {col}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    /// where beams start: entry points, and any beams already drawn in the input
+    Source(Pos),
+    Splitter(Pos),
+    Deflector(Pos),
+    /// where beams are caught, so nothing leaves it
+    Absorber(Pos),
+    /// where beams leave the bottom of the manifold
+    Exit,
+}
+
+/// the manifold as a DAG, where each source feeds the nearest splitter below it, and each splitter
+/// feeds the nearest splitters below on its left and right. deflectors are nodes like splitters,
+/// feeding only one side, and absorbers are nodes which feed nothing. beams which leave the bottom
+/// go to a single exit node.
+struct SplitterGraph {
+    /// each node, and the number of timelines in which a beam reaches it
+    nodes: Vec<(Node, u64)>,
+    /// `(from, to)` indices into `nodes`, in order of `from`
+    edges: Vec<(usize, usize)>,
+}
+
+impl SplitterGraph {
+    fn new(manifold: &TachyonManifold, beams: &BeamLayer) -> Self {
+        let mut nodes: Vec<_> = manifold
+            .cells
+            .iter()
+            .filter_map(|(pos, kind)| {
+                let node = match kind {
+                    Kind::EntryPoint | Kind::Beam if manifold.is_source(beams, pos) => {
+                        Node::Source(pos)
+                    }
+                    Kind::EntryPoint | Kind::Beam => return None,
+                    Kind::Splitter => Node::Splitter(pos),
+                    Kind::DeflectorLeft | Kind::DeflectorRight => Node::Deflector(pos),
+                    Kind::Absorber => Node::Absorber(pos),
+                    Kind::Air | Kind::Wall => return None,
+                };
+                Some((node, beams.timelines[pos]))
+            })
+            .collect();
+        let exit = nodes.len();
+        nodes.push((Node::Exit, exiting_timelines(manifold, beams)));
+
+        let index: HashMap<_, _> = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, (node, _))| match node {
+                Node::Splitter(pos) | Node::Deflector(pos) | Node::Absorber(pos) => {
+                    Some((*pos, idx))
+                }
+                Node::Source(_) | Node::Exit => None,
+            })
+            .collect();
+        let below = |pos: Pos, dir: Dir| manifold.cells.step(pos, dir, Boundary::Bounded);
+        // the node a beam carrying straight down into this cell reaches, if it isn't blocked.
+        // `None` is past the bottom of the manifold.
+        let fall_from = |mut pos: Option<Pos>| -> Option<usize> {
+            while let Some(here) = pos {
+                match manifold.cells[here] {
+                    Kind::Wall => return None,
+                    kind if kind.passes_through() => pos = below(here, Dir::S),
+                    _ => return index.get(&here).copied(),
+                }
+            }
+            Some(exit)
+        };
+        // the node a beam sent diagonally out of this cell reaches, if it doesn't go off the side,
+        // or land on a cell which only takes beams from straight above
+        let land_on = |from: Pos, dir: Dir| -> Option<usize> {
+            if from.row + 1 == manifold.cells.rows() {
+                return Some(exit);
+            }
+            let pos = below(from, dir)?;
+            manifold.cells[pos]
+                .accepts_diagonal()
+                .then(|| fall_from(Some(pos)))
+                .flatten()
+        };
+
+        let mut edges = Vec::new();
+        for (idx, (node, _)) in nodes.iter().enumerate() {
+            let targets = match *node {
+                Node::Source(pos) => [fall_from(below(pos, Dir::S)), None],
+                Node::Splitter(pos) | Node::Deflector(pos) => {
+                    let [left, _, right] = manifold.cells[pos].exits();
+                    [
+                        left.then(|| land_on(pos, Dir::SW)).flatten(),
+                        right.then(|| land_on(pos, Dir::SE)).flatten(),
+                    ]
+                }
+                Node::Absorber(_) | Node::Exit => [None, None],
+            };
+            edges.extend(targets.into_iter().flatten().map(|to| (idx, to)));
+        }
+        Self { nodes, edges }
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph splitters {\n");
+        for (idx, (node, timelines)) in self.nodes.iter().enumerate() {
+            let (label, style) = match node {
+                Node::Source(pos) => (format!("S {pos}"), "shape=box"),
+                Node::Splitter(pos) if *timelines == 0 => (pos.to_string(), "style=dashed"),
+                Node::Splitter(pos) => (pos.to_string(), "style=solid"),
+                Node::Deflector(pos) => (pos.to_string(), "shape=triangle"),
+                Node::Absorber(pos) => (format!("@ {pos}"), "shape=octagon"),
+                Node::Exit => ("exit".to_string(), "shape=doublecircle"),
+            };
+            writeln!(dot, r#"  n{idx} [label="{label}\n{timelines}", {style}];"#).unwrap();
+        }
+        for (from, to) in &self.edges {
+            writeln!(dot, "  n{from} -> n{to};").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_json(&self) -> String {
+        let nodes = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, (node, timelines))| {
+                let next = self
+                    .edges
+                    .iter()
+                    .filter(|(from, _)| *from == idx)
+                    .map(|(_, to)| to)
+                    .join(",");
+                let place = match node {
+                    Node::Source(Pos { row, col }) => {
+                        format!(r#""kind":"source","row":{row},"col":{col}"#)
+                    }
+                    Node::Splitter(Pos { row, col }) => {
+                        format!(r#""kind":"splitter","row":{row},"col":{col}"#)
+                    }
+                    Node::Deflector(Pos { row, col }) => {
+                        format!(r#""kind":"deflector","row":{row},"col":{col}"#)
+                    }
+                    Node::Absorber(Pos { row, col }) => {
+                        format!(r#""kind":"absorber","row":{row},"col":{col}"#)
+                    }
+                    Node::Exit => r#""kind":"exit""#.to_string(),
+                };
+                format!(r#"{{"id":{idx},{place},"timelines":{timelines},"next":[{next}]}}"#)
+            })
+            .join(",");
+        format!(r#"{{"nodes":[{nodes}]}}"#)
+    }
+}
+
+/// a manifold drawn with its beams. splitters which a beam reaches are drawn as `^`, and those
+/// which none does as `x`.
+struct Diagram<'a> {
+    manifold: &'a TachyonManifold,
+    beams: &'a BeamLayer,
+    /// only rows before this have their beams drawn, and their splitters marked as hit or not
+    rows_done: usize,
+    colour: bool,
+}
+
+impl<'a> Diagram<'a> {
+    fn up_to_row(self, rows_done: usize) -> Self {
+        Self { rows_done, ..self }
+    }
+
+    fn coloured(self, colour: bool) -> Self {
+        Self { colour, ..self }
+    }
+}
+
+impl Display for Diagram<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const BEAM: &str = "\x1b[33m";
+        const HIT: &str = "\x1b[1;31m";
+        const UNHIT: &str = "\x1b[2m";
+        const ENTRY: &str = "\x1b[1;32m";
+        const RESET: &str = "\x1b[0m";
+
+        for (row_idx, row) in self.manifold.cells.iter_rows().enumerate() {
+            if row_idx > 0 {
+                f.write_char('\n')?;
             }
-            for cell in row {
-                write!(f, "{cell}")?;
+            let done = row_idx < self.rows_done;
+            for (col, cell) in row.iter().enumerate() {
+                let beam = done && self.beams.has_beam(Pos::new(row_idx, col));
+                let (c, colour) = match cell {
+                    Kind::Air if beam => ('|', BEAM),
+                    Kind::Air => ('.', ""),
+                    Kind::Splitter if beam => ('^', HIT),
+                    Kind::Splitter if done => ('x', UNHIT),
+                    Kind::Splitter => ('^', ""),
+                    Kind::EntryPoint => ('S', ENTRY),
+                    Kind::Beam => ('|', BEAM),
+                    Kind::DeflectorLeft | Kind::DeflectorRight | Kind::Absorber => {
+                        let c = cell.to_string().chars().next().unwrap();
+                        (c, if beam { HIT } else { "" })
+                    }
+                    Kind::Wall => ('#', ""),
+                };
+                match self.colour && !colour.is_empty() {
+                    true => write!(f, "{colour}{c}{RESET}")?,
+                    false => f.write_char(c)?,
+                }
             }
         }
         Ok(())
     }
 }
 
+impl Display for TachyonManifold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cells)
+    }
+}
+
 mod parse {
     use super::{Kind, TachyonManifold};
+    use common::grid::{Grid, ParseError};
 
-    use winnow::{ascii::*, combinator::*, prelude::*, token::*};
+    fn manifold_cell(c: char) -> Option<Kind> {
+        match c {
+            '.' => Some(Kind::Air),
+            // diagrams draw splitters no beam reaches as `x`
+            '^' | 'x' => Some(Kind::Splitter),
+            'S' => Some(Kind::EntryPoint),
+            '|' => Some(Kind::Beam),
+            '/' => Some(Kind::DeflectorLeft),
+            '\\' => Some(Kind::DeflectorRight),
+            '@' => Some(Kind::Absorber),
+            '#' => Some(Kind::Wall),
+            _ => None,
+        }
+    }
 
-    fn manifold_cell(input: &mut &str) -> ModalResult<Kind> {
-        alt((
-            '.'.value(Kind::Air),
-            '^'.value(Kind::Splitter { hit: false }),
-            'S'.value(Kind::EntryPoint),
-            '|'.value(Kind::Beam),
-        ))
-        .parse_next(input)
+    /// rows may end with either kind of line ending, and the last one doesn't need one at all
+    pub fn tachyon_manifold(input: &str) -> Result<TachyonManifold, ParseError> {
+        let cells = Grid::parse(input, manifold_cell)?;
+        Ok(TachyonManifold { cells })
     }
+}
 
-    pub fn tachyon_manifold(input: &mut &str) -> ModalResult<TachyonManifold> {
-        repeat(
-            1..,
-            terminated(repeat(1.., manifold_cell).map(|v: Vec<_>| v), line_ending),
-        )
-        .map(|rows| TachyonManifold { rows })
-        .parse_next(input)
+#[cfg(test)]
+const SAMPLE: &str = "\
+.......S.......
+...............
+.......^.......
+...............
+......^.^......
+...............
+.....^.^.^.....
+...............
+....^.^...^....
+...............
+...^.^...^.^...
+...............
+..^...^.....^..
+...............
+.^.^.^.^.^...^.
+...............
+";
+
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE, 21, 40)]
+#[case::no_splitters("..S..\n.....\n", 0, 1)]
+#[case::one_splitter("..S..\n..^..\n.....\n", 1, 2)]
+#[case::splitter_on_last_row("..S..\n..^..\n", 1, 2)]
+#[case::beams_merge("..S.S..\n..^.^..\n.......\n", 2, 4)]
+// beams leave a splitter diagonally, so they skip past a splitter right beside it
+#[case::beam_into_splitter_is_lost("...S...\n...^...\n..^.^..\n.......\n", 1, 0)]
+#[case::splitter_at_edge("S..\n^..\n...\n", 1, 1)]
+#[case::drawn_beam_source("..|..\n..^..\n.....\n", 1, 2)]
+#[case::drawn_beam_fed_from_above("S\n|\n|\n", 0, 1)]
+#[case::drawn_beams_fed_by_splitter("..S..\n..^..\n.|.|.\n", 1, 2)]
+fn test_parts(#[case] input: &str, #[case] expected_pt1: u64, #[case] expected_pt2: u64) {
+    let manifold = parse::tachyon_manifold(input).unwrap();
+    let beams = manifold.simulate();
+    assert_eq!(expected_pt1, part1(&manifold, &beams));
+    assert_eq!(expected_pt2, part2(&manifold, &beams));
+}
+
+#[cfg(test)]
+#[test]
+fn test_simulate_leaves_manifold_alone() {
+    let manifold = parse::tachyon_manifold(SAMPLE).unwrap();
+    let beams = manifold.simulate();
+    assert_eq!(SAMPLE.trim_end(), manifold.to_string());
+    assert_eq!(beams.timelines, manifold.simulate().timelines);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::all_rows(4, "..S..\n..^..\n.|.|.\n.|x|.")]
+#[case::partway(2, "..S..\n..^..\n.....\n..^..")]
+#[case::before_any(0, "..S..\n..^..\n.....\n..^..")]
+fn test_diagram(#[case] rows_done: usize, #[case] expected: &str) {
+    let manifold = parse::tachyon_manifold("..S..\n..^..\n.....\n..^..\n").unwrap();
+    let beams = manifold.simulate();
+    let diagram = manifold.diagram(&beams).up_to_row(rows_done);
+    assert_eq!(expected, diagram.to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_diagram_colour() {
+    let manifold = parse::tachyon_manifold("S\n^\n").unwrap();
+    let beams = manifold.simulate();
+    assert_eq!(
+        "\x1b[1;32mS\x1b[0m\n\x1b[1;31m^\x1b[0m",
+        manifold.diagram(&beams).coloured(true).to_string()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_splitter_report() {
+    let manifold = parse::tachyon_manifold("..S...\n..^...\n......\n.^.^.^\n").unwrap();
+    let report = SplitterReport::new(&manifold, &manifold.simulate());
+    assert_eq!(
+        SplitterReport {
+            hit: vec![
+                (Pos::new(1, 2), 1),
+                (Pos::new(3, 1), 1),
+                (Pos::new(3, 3), 1)
+            ],
+            unreached: vec![Pos::new(3, 5)],
+        },
+        report
+    );
+    assert_eq!(
+        concat!(
+            r#"{"hit":[{"row":1,"col":2,"timelines":1},{"row":3,"col":1,"timelines":1},"#,
+            r#"{"row":3,"col":3,"timelines":1}],"unreached":[{"row":3,"col":5}]}"#
+        ),
+        report.to_json()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_splitter_report_sample() {
+    let manifold = parse::tachyon_manifold(SAMPLE).unwrap();
+    let report = SplitterReport::new(&manifold, &manifold.simulate());
+    assert_eq!(21, report.hit.len());
+    assert_eq!(vec![Pos::new(14, 9)], report.unreached);
+}
+
+#[cfg(test)]
+#[test]
+fn test_splitter_graph() {
+    let manifold = parse::tachyon_manifold("..S...\n..^...\n......\n.^....\n..^...\n").unwrap();
+    let graph = SplitterGraph::new(&manifold, &manifold.simulate());
+    assert_eq!(
+        vec![
+            (Node::Source(Pos::new(0, 2)), 1),
+            (Node::Splitter(Pos::new(1, 2)), 1),
+            (Node::Splitter(Pos::new(3, 1)), 1),
+            (Node::Splitter(Pos::new(4, 2)), 0),
+            (Node::Exit, 2),
+        ],
+        graph.nodes
+    );
+    // (4, 2) is right beside (3, 1), so the beam going right from (3, 1) misses it
+    assert_eq!(
+        vec![(0, 1), (1, 2), (1, 4), (2, 4), (3, 4), (3, 4)],
+        graph.edges
+    );
+    assert_eq!(
+        concat!(
+            r#"{"nodes":[{"id":0,"kind":"source","row":0,"col":2,"timelines":1,"next":[1]},"#,
+            r#"{"id":1,"kind":"splitter","row":1,"col":2,"timelines":1,"next":[2,4]},"#,
+            r#"{"id":2,"kind":"splitter","row":3,"col":1,"timelines":1,"next":[4]},"#,
+            r#"{"id":3,"kind":"splitter","row":4,"col":2,"timelines":0,"next":[4,4]},"#,
+            r#"{"id":4,"kind":"exit","timelines":2,"next":[]}]}"#,
+        ),
+        graph.to_json()
+    );
+}
+
+/// every reached splitter's timelines come from the nodes feeding it
+#[cfg(test)]
+#[test]
+fn test_splitter_graph_timelines_flow() {
+    let manifold = parse::tachyon_manifold(SAMPLE).unwrap();
+    let graph = SplitterGraph::new(&manifold, &manifold.simulate());
+    assert_timelines_flow(&graph);
+    assert_eq!((Node::Exit, 40), *graph.nodes.last().unwrap());
+}
+
+#[cfg(test)]
+fn assert_timelines_flow(graph: &SplitterGraph) {
+    for (idx, (node, timelines)) in graph.nodes.iter().enumerate() {
+        let incoming: u64 = graph
+            .edges
+            .iter()
+            .filter(|(_, to)| *to == idx)
+            .map(|(from, _)| graph.nodes[*from].1)
+            .sum();
+        match node {
+            Node::Source(_) => assert_eq!(0, incoming),
+            _ => assert_eq!(*timelines, incoming, "{node:?}"),
+        }
     }
 }
+
+/// a manifold's diagram, beams and all, reads back in with the same answers and splitter graph
+#[cfg(test)]
+#[rstest]
+#[case::sample(SAMPLE)]
+#[case::one_splitter("..S..\n..^..\n.....\n")]
+#[case::deflector("..S..\n../..\n.....\n")]
+#[case::absorber_and_exit("..S..\n..^..\n.@...\n.....\n")]
+#[case::drawn_beam("..|..\n..^..\n.....\n")]
+fn test_diagram_reads_back(#[case] input: &str) {
+    let manifold = parse::tachyon_manifold(input).unwrap();
+    let beams = manifold.simulate();
+    let diagram = manifold.diagram(&beams).to_string();
+    let reread = parse::tachyon_manifold(&diagram).unwrap();
+    let reread_beams = reread.simulate();
+    assert_eq!(part1(&manifold, &beams), part1(&reread, &reread_beams));
+    assert_eq!(part2(&manifold, &beams), part2(&reread, &reread_beams));
+    let graph = SplitterGraph::new(&reread, &reread_beams);
+    assert_timelines_flow(&graph);
+    assert_eq!(
+        SplitterGraph::new(&manifold, &beams).to_json(),
+        graph.to_json()
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::deflector_left("..S..\n../..\n.....\n", ".|...", 0, 1)]
+#[case::deflector_right("..S..\n..\\..\n.....\n", "...|.", 0, 1)]
+#[case::deflector_on_last_row("..S..\n../..\n", "../..", 0, 1)]
+#[case::deflector_into_splitter_is_lost("..S..\n../..\n.^...\n", ".x...", 0, 0)]
+#[case::absorber("..S..\n..@..\n.....\n", ".....", 0, 1)]
+#[case::absorber_catches_diagonal("..S..\n..^..\n.@...\n", ".@.|.", 1, 2)]
+#[case::absorber_and_exit("..S..\n..^..\n.@...\n.....\n", "...|.", 1, 2)]
+#[case::wall("..S..\n..#..\n.....\n", ".....", 0, 0)]
+#[case::wall_blocks_diagonal("..S..\n..^..\n.#...\n.....\n", "...|.", 1, 1)]
+#[case::multiple_entry_points("S...S\n^...^\n.....\n", ".|.|.", 2, 2)]
+#[case::entry_points_miss_splitter("S.S\n...\n.^.\n", "|x|", 0, 2)]
+fn test_extended_cells(
+    #[case] input: &str,
+    #[case] expected_last_row: &str,
+    #[case] expected_pt1: u64,
+    #[case] expected_pt2: u64,
+) {
+    let manifold = parse::tachyon_manifold(input).unwrap();
+    let beams = manifold.simulate();
+    let diagram = manifold.diagram(&beams).to_string();
+    assert_eq!(expected_last_row, diagram.lines().last().unwrap());
+    assert_eq!(expected_pt1, part1(&manifold, &beams));
+    assert_eq!(expected_pt2, part2(&manifold, &beams));
+}
+
+#[cfg(test)]
+#[test]
+fn test_extended_cells_display_round_trips() {
+    let input = "S./\\@#^|\n";
+    let manifold = parse::tachyon_manifold(input).unwrap();
+    assert_eq!(input.trim_end(), manifold.to_string());
+}
+
+#[cfg(test)]
+#[test]
+fn test_splitter_graph_extended_cells() {
+    let manifold =
+        parse::tachyon_manifold("...S...\n...^...\n.......\n../.\\..\n.......\n.@...#.\n.......\n")
+            .unwrap();
+    let beams = manifold.simulate();
+    let graph = SplitterGraph::new(&manifold, &beams);
+    assert_eq!(
+        vec![
+            (Node::Source(Pos::new(0, 3)), 1),
+            (Node::Splitter(Pos::new(1, 3)), 1),
+            (Node::Deflector(Pos::new(3, 2)), 1),
+            (Node::Deflector(Pos::new(3, 4)), 1),
+            (Node::Absorber(Pos::new(5, 1)), 1),
+            (Node::Exit, 0),
+        ],
+        graph.nodes
+    );
+    // the right deflector's beam runs into the wall
+    assert_eq!(vec![(0, 1), (1, 2), (1, 3), (2, 4)], graph.edges);
+    assert_eq!(1, part2(&manifold, &beams));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::trailing_newline("S.\n^.\n")]
+#[case::no_trailing_newline("S.\n^.")]
+#[case::crlf("S.\r\n^.\r\n")]
+#[case::crlf_no_trailing_newline("S.\r\n^.")]
+fn test_parse_line_endings(#[case] input: &str) {
+    let manifold = parse::tachyon_manifold(input).unwrap();
+    assert_eq!("S.\n^.", manifold.to_string());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::short_row("S..\n^.\n...\n", 2, 3, 2)]
+#[case::long_last_row("S..\n^..\n....", 3, 3, 4)]
+#[case::crlf("S..\r\n^..\r\n.\r\n", 3, 3, 1)]
+#[case::blank_line("S.\n\n^.\n", 2, 2, 0)]
+fn test_parse_ragged(
+    #[case] input: &str,
+    #[case] row: usize,
+    #[case] expected: usize,
+    #[case] found: usize,
+) {
+    assert_eq!(
+        Err(ParseError::RaggedRow {
+            row,
+            expected,
+            found
+        }),
+        parse::tachyon_manifold(input).map(|manifold| manifold.cells)
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::empty("", ParseError::Empty)]
+#[case::blank_line("S.\n\n^.\n", ParseError::RaggedRow { row: 2, expected: 2, found: 0 })]
+#[case::unknown_cell("S.\n^?\n", ParseError::UnknownCell { row: 2, col: 2, found: '?' })]
+fn test_parse_invalid(#[case] input: &str, #[case] expected: ParseError) {
+    assert_eq!(
+        Err(expected),
+        parse::tachyon_manifold(input).map(|manifold| manifold.cells)
+    );
+}
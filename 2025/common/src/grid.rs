@@ -0,0 +1,521 @@
+//! a rectangular grid of cells stored row by row, with typed positions and directions for moving
+//! around it.
+
+#[cfg(test)]
+use rstest::rstest;
+use std::{
+    fmt::Display,
+    ops::{Index, IndexMut},
+};
+
+/// a cell's position, counting rows down from the top and columns right from the left, both from 0
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pos {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Pos {
+    pub const fn new(row: usize, col: usize) -> Self {
+        Self { row, col }
+    }
+
+    /// the position `(drow, dcol)` away, on a grid of the given `(rows, cols)` size. `None` if
+    /// that falls past the edges of a bounded grid.
+    pub fn offset(
+        self,
+        (drow, dcol): (isize, isize),
+        (rows, cols): (usize, usize),
+        boundary: Boundary,
+    ) -> Option<Pos> {
+        match boundary {
+            Boundary::Bounded => {
+                let row = self
+                    .row
+                    .checked_add_signed(drow)
+                    .filter(|&row| row < rows)?;
+                let col = self
+                    .col
+                    .checked_add_signed(dcol)
+                    .filter(|&col| col < cols)?;
+                Some(Pos { row, col })
+            }
+            Boundary::Wrapping => (rows > 0 && cols > 0).then(|| Pos {
+                row: (self.row as isize + drow).rem_euclid(rows as isize) as usize,
+                col: (self.col as isize + dcol).rem_euclid(cols as isize) as usize,
+            }),
+        }
+    }
+
+    /// the neighbouring position in the given direction. see [`Pos::offset`].
+    pub fn step(self, dir: Dir, size: (usize, usize), boundary: Boundary) -> Option<Pos> {
+        self.offset(dir.offset(), size, boundary)
+    }
+}
+
+impl From<(usize, usize)> for Pos {
+    fn from((row, col): (usize, usize)) -> Self {
+        Self { row, col }
+    }
+}
+
+impl Display for Pos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.row, self.col)
+    }
+}
+
+/// one of the eight directions to a neighbouring cell. north is towards row 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dir {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Dir {
+    /// `(drow, dcol)`
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Dir::N => (-1, 0),
+            Dir::NE => (-1, 1),
+            Dir::E => (0, 1),
+            Dir::SE => (1, 1),
+            Dir::S => (1, 0),
+            Dir::SW => (1, -1),
+            Dir::W => (0, -1),
+            Dir::NW => (-1, -1),
+        }
+    }
+}
+
+/// what lies past the edges of a grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// nothing
+    #[default]
+    Bounded,
+    /// the opposite edge, so the grid is a torus
+    Wrapping,
+}
+
+/// which cells count as neighbours
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    /// the orthogonally adjacent cells, or further out, every cell within a manhattan distance
+    /// (a von Neumann neighbourhood)
+    Four,
+    /// the orthogonally and diagonally adjacent cells, or further out, every cell within a square
+    /// (a Moore neighbourhood)
+    #[default]
+    Eight,
+}
+
+impl Connectivity {
+    /// `(drow, dcol)` offsets to every neighbour out to `radius` cells away, row by row. a cell
+    /// is never its own neighbour.
+    pub fn offsets(self, radius: usize) -> impl Iterator<Item = (isize, isize)> {
+        let r = radius as isize;
+        (-r..=r)
+            .flat_map(move |drow| (-r..=r).map(move |dcol| (drow, dcol)))
+            .filter(move |&(drow, dcol)| match self {
+                Connectivity::Four => drow.abs() + dcol.abs() <= r,
+                Connectivity::Eight => true,
+            })
+            .filter(|&offset| offset != (0, 0))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// no rows, or a first row with no cells
+    Empty,
+    UnknownCell {
+        /// 1-based, to match how editors number lines
+        row: usize,
+        /// 1-based
+        col: usize,
+        found: char,
+    },
+    RaggedRow {
+        /// 1-based
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "grid has no cells"),
+            ParseError::UnknownCell { row, col, found } => {
+                write!(f, "unexpected `{found}` at row {row}, column {col}")
+            }
+            ParseError::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {row} has {found} cells, but expected {expected} (the width of row 1)"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    rows: usize,
+    cols: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(rows: usize, cols: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            rows,
+            cols,
+            cells: vec![fill; rows * cols],
+        }
+    }
+
+    pub fn from_fn(rows: usize, cols: usize, mut f: impl FnMut(Pos) -> T) -> Self {
+        let cells = (0..rows * cols)
+            .map(|idx| f(Pos::new(idx / cols, idx % cols)))
+            .collect();
+        Self { rows, cols, cells }
+    }
+
+    /// fails with [`ParseError::RaggedRow`] unless every row is as wide as the first
+    pub fn from_rows(rows: impl IntoIterator<Item = Vec<T>>) -> Result<Self, ParseError> {
+        let mut grid = Self {
+            rows: 0,
+            cols: 0,
+            cells: Vec::new(),
+        };
+        for (idx, mut row) in rows.into_iter().enumerate() {
+            if idx == 0 {
+                grid.cols = row.len();
+            } else if row.len() != grid.cols {
+                return Err(ParseError::RaggedRow {
+                    row: idx + 1,
+                    expected: grid.cols,
+                    found: row.len(),
+                });
+            }
+            grid.cells.append(&mut row);
+            grid.rows += 1;
+        }
+        Ok(grid)
+    }
+
+    /// like [`Grid::from_rows`], but rows shorter than the longest are filled out with `fill`
+    pub fn from_rows_padded(rows: impl IntoIterator<Item = Vec<T>>, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        let mut rows: Vec<_> = rows.into_iter().collect();
+        let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut grid = Self {
+            rows: rows.len(),
+            cols,
+            cells: Vec::with_capacity(rows.len() * cols),
+        };
+        for row in &mut rows {
+            row.resize(cols, fill.clone());
+            grid.cells.append(row);
+        }
+        grid
+    }
+
+    /// parses a line per row and a char per cell, with `cell` giving what each char means, or
+    /// `None` if it isn't allowed. either kind of line ending is fine, as is a missing final one.
+    pub fn parse(input: &str, cell: impl FnMut(char) -> Option<T>) -> Result<Self, ParseError> {
+        let rows = parse_rows(input, cell).collect::<Result<Vec<_>, _>>()?;
+        if rows.first().is_none_or(Vec::is_empty) {
+            return Err(ParseError::Empty);
+        }
+        Self::from_rows(rows)
+    }
+
+    /// like [`Grid::parse`], but rows may be of differing widths, with short rows filled out with
+    /// `fill`
+    pub fn parse_padded(
+        input: &str,
+        cell: impl FnMut(char) -> Option<T>,
+        fill: T,
+    ) -> Result<Self, ParseError>
+    where
+        T: Clone,
+    {
+        let rows = parse_rows(input, cell).collect::<Result<Vec<_>, _>>()?;
+        match Self::from_rows_padded(rows, fill) {
+            grid if grid.cols == 0 => Err(ParseError::Empty),
+            grid => Ok(grid),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// `(rows, cols)`
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.row < self.rows && pos.col < self.cols
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        self.contains(pos)
+            .then(|| &self.cells[pos.row * self.cols + pos.col])
+    }
+
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        self.contains(pos)
+            .then(|| &mut self.cells[pos.row * self.cols + pos.col])
+    }
+
+    /// every position, row by row
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + use<T> {
+        let cols = self.cols;
+        (0..self.rows * cols).map(move |idx| Pos::new(idx / cols, idx % cols))
+    }
+
+    /// every cell along with its position, row by row
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, &T)> {
+        self.positions().zip(&self.cells)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Pos, &mut T)> {
+        self.positions().zip(&mut self.cells)
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.cells[row * self.cols..][..self.cols]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut [T] {
+        &mut self.cells[row * self.cols..][..self.cols]
+    }
+
+    /// the cells of column `col`, from top to bottom
+    pub fn col(&self, col: usize) -> impl DoubleEndedIterator<Item = &T> {
+        assert!(col < self.cols, "column {col} out of range");
+        self.cells[col..].iter().step_by(self.cols)
+    }
+
+    pub fn iter_rows(&self) -> impl DoubleEndedIterator<Item = &[T]> {
+        (0..self.rows).map(|row| self.row(row))
+    }
+
+    pub fn iter_cols(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = impl DoubleEndedIterator<Item = &T>> {
+        (0..self.cols).map(|col| self.col(col))
+    }
+
+    /// see [`Pos::offset`]
+    pub fn offset(&self, pos: Pos, offset: (isize, isize), boundary: Boundary) -> Option<Pos> {
+        pos.offset(offset, self.size(), boundary)
+    }
+
+    /// see [`Pos::step`]
+    pub fn step(&self, pos: Pos, dir: Dir, boundary: Boundary) -> Option<Pos> {
+        pos.step(dir, self.size(), boundary)
+    }
+
+    /// the adjacent cells of `pos`, row by row. on a wrapping grid less than 3 cells across the
+    /// same cell can turn up more than once, or be its own neighbour.
+    pub fn neighbours(
+        &self,
+        pos: Pos,
+        connectivity: Connectivity,
+        boundary: Boundary,
+    ) -> impl Iterator<Item = Pos> + use<T> {
+        let size = self.size();
+        connectivity
+            .offsets(1)
+            .filter_map(move |offset| pos.offset(offset, size, boundary))
+    }
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos)
+            .unwrap_or_else(|| panic!("{pos} out of range for a {}×{} grid", self.rows, self.cols))
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        let (rows, cols) = self.size();
+        self.get_mut(pos)
+            .unwrap_or_else(|| panic!("{pos} out of range for a {rows}×{cols} grid"))
+    }
+}
+
+/// each cell's `Display`, with a line per row and no trailing newline
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, row) in self.iter_rows().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{cell}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// each line's cells, with `cell` giving what each char means, for building something other than
+/// a [`Grid`] from them. rows aren't checked to be the same width.
+pub fn parse_rows<T>(
+    input: &str,
+    mut cell: impl FnMut(char) -> Option<T>,
+) -> impl Iterator<Item = Result<Vec<T>, ParseError>> {
+    input
+        .lines()
+        .enumerate()
+        .map(move |(row, line)| parse_row(row, line, &mut cell))
+}
+
+/// the cells of one line, for reading an input a line at a time. `row` is the line's 0-based
+/// index in the input, for errors.
+pub fn parse_row<T>(
+    row: usize,
+    line: &str,
+    mut cell: impl FnMut(char) -> Option<T>,
+) -> Result<Vec<T>, ParseError> {
+    line.chars()
+        .enumerate()
+        .map(|(col, found)| {
+            cell(found).ok_or(ParseError::UnknownCell {
+                row: row + 1,
+                col: col + 1,
+                found,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn digits(input: &str) -> Grid<u32> {
+    Grid::parse(input, |c| c.to_digit(10)).unwrap()
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("12\n34\n")]
+#[case::crlf("12\r\n34\r\n")]
+#[case::no_trailing_newline("12\n34")]
+fn test_parse(#[case] input: &str) {
+    let grid = digits(input);
+    assert_eq!((2, 2), grid.size());
+    assert_eq!(3, grid[Pos::new(1, 0)]);
+    assert_eq!("12\n34", grid.to_string());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::empty("", ParseError::Empty)]
+#[case::blank_first_row("\n12\n", ParseError::Empty)]
+#[case::unknown("12\n3x\n", ParseError::UnknownCell { row: 2, col: 2, found: 'x' })]
+#[case::short_row("123\n12\n", ParseError::RaggedRow { row: 2, expected: 3, found: 2 })]
+#[case::blank_line("12\n\n12\n", ParseError::RaggedRow { row: 2, expected: 2, found: 0 })]
+fn test_parse_error(#[case] input: &str, #[case] expected: ParseError) {
+    assert_eq!(Err(expected), Grid::parse(input, |c| c.to_digit(10)));
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::padded("12\n3\n\n456\n", Ok("120\n300\n000\n456"))]
+#[case::crlf("1\r\n23\r\n", Ok("10\n23"))]
+#[case::empty("\n\n", Err(ParseError::Empty))]
+// the position is in the text as written, not as padded
+#[case::unknown("1\n\n12x\n", Err(ParseError::UnknownCell { row: 3, col: 3, found: 'x' }))]
+fn test_parse_padded(#[case] input: &str, #[case] expected: Result<&str, ParseError>) {
+    assert_eq!(
+        expected.map(str::to_string),
+        Grid::parse_padded(input, |c| c.to_digit(10), 0).map(|grid| grid.to_string())
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_rows_and_cols() {
+    let grid = digits("123\n456\n");
+    assert_eq!([4, 5, 6], grid.row(1));
+    assert_eq!(vec![&3, &6], grid.col(2).collect::<Vec<_>>());
+    assert_eq!(
+        vec![vec![1, 4], vec![2, 5], vec![3, 6]],
+        grid.iter_cols()
+            .map(|col| col.copied().collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some((Pos::new(1, 2), &6)),
+        grid.iter().find(|&(_, &n)| n == 6)
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::four_bounded_corner(Connectivity::Four, Boundary::Bounded, (0, 0), vec![(0, 1), (1, 0)])]
+#[case::four_wrapping_corner(Connectivity::Four, Boundary::Wrapping, (0, 0), vec![(2, 0), (0, 3), (0, 1), (1, 0)])]
+#[case::eight_bounded_edge(Connectivity::Eight, Boundary::Bounded, (2, 1), vec![(1, 0), (1, 1), (1, 2), (2, 0), (2, 2)])]
+#[case::eight_wrapping_corner(
+    Connectivity::Eight,
+    Boundary::Wrapping,
+    (2, 3),
+    vec![(1, 2), (1, 3), (1, 0), (2, 2), (2, 0), (0, 2), (0, 3), (0, 0)]
+)]
+fn test_neighbours(
+    #[case] connectivity: Connectivity,
+    #[case] boundary: Boundary,
+    #[case] pos: (usize, usize),
+    #[case] expected: Vec<(usize, usize)>,
+) {
+    let grid = Grid::new(3, 4, ());
+    assert_eq!(
+        expected.into_iter().map(Pos::from).collect::<Vec<_>>(),
+        grid.neighbours(pos.into(), connectivity, boundary)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::four_1(Connectivity::Four, 1, 4)]
+#[case::four_2(Connectivity::Four, 2, 12)]
+#[case::eight_1(Connectivity::Eight, 1, 8)]
+#[case::eight_2(Connectivity::Eight, 2, 24)]
+#[case::radius_0(Connectivity::Eight, 0, 0)]
+fn test_connectivity_offsets(
+    #[case] connectivity: Connectivity,
+    #[case] radius: usize,
+    #[case] expected: usize,
+) {
+    let offsets = connectivity.offsets(radius).collect::<Vec<_>>();
+    assert_eq!(expected, offsets.len());
+    assert!(!offsets.contains(&(0, 0)));
+}
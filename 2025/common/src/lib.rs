@@ -0,0 +1,4 @@
+//! helpers shared between the 2025 days
+
+pub mod grid;
+pub mod parse;
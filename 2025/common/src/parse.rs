@@ -0,0 +1,247 @@
+//! winnow combinators for the shapes puzzle inputs keep coming in. all of them take either kind of
+//! line ending, and don't mind whether the last line has one.
+
+use crate::grid::Grid;
+#[cfg(test)]
+use rstest::rstest;
+use std::ops::RangeInclusive;
+use winnow::{
+    Parser,
+    ascii::{Uint, dec_uint, line_ending, space0, space1},
+    combinator::{alt, delimited, eof, repeat, separated, separated_pair, terminated},
+    error::ParserError,
+    token::take_till,
+};
+
+/// `item`, then the end of its line
+pub fn line<'a, O, E, P>(item: P) -> impl Parser<&'a str, O, E>
+where
+    E: ParserError<&'a str>,
+    P: Parser<&'a str, O, E>,
+{
+    terminated(item, alt((line_ending, eof)))
+}
+
+/// `item` once per line, for as many lines as it matches. `item` mustn't match an empty line,
+/// or it'd match forever at the end of the input. to fold the items as they're parsed rather
+/// than collect them, use [`line`] with [`repeat`].
+pub fn lines<'a, O, E, P>(item: P) -> impl Parser<&'a str, Vec<O>, E>
+where
+    E: ParserError<&'a str>,
+    P: Parser<&'a str, O, E>,
+{
+    repeat(0.., line(item))
+}
+
+/// `lo-hi`
+pub fn range_inclusive<'a, T, E>() -> impl Parser<&'a str, RangeInclusive<T>, E>
+where
+    T: Uint,
+    E: ParserError<&'a str>,
+{
+    separated_pair(dec_uint, '-', dec_uint).map(|(lo, hi)| lo..=hi)
+}
+
+/// a line per row and a char per cell, with `cell` giving what each char means, or `None` if it
+/// isn't allowed. the grid ends at the first blank line, and every row must be as wide as the
+/// first. see [`Grid::parse`] for parsing a whole input with errors saying what was wrong.
+pub fn char_grid<'a, T, E>(
+    mut cell: impl FnMut(char) -> Option<T>,
+) -> impl Parser<&'a str, Grid<T>, E>
+where
+    E: ParserError<&'a str>,
+{
+    let row = take_till(1.., ['\r', '\n'])
+        .verify_map(move |line: &str| line.chars().map(&mut cell).collect::<Option<Vec<T>>>());
+    lines(row).verify_map(|rows: Vec<Vec<T>>| match rows.is_empty() {
+        true => None,
+        false => Grid::from_rows(rows).ok(),
+    })
+}
+
+/// a section of `first` lines, then a blank line, then a section of `second` lines
+pub fn blank_line_sections<'a, O1, O2, E, P1, P2>(
+    first: P1,
+    second: P2,
+) -> impl Parser<&'a str, (Vec<O1>, Vec<O2>), E>
+where
+    E: ParserError<&'a str>,
+    P1: Parser<&'a str, O1, E>,
+    P2: Parser<&'a str, O2, E>,
+{
+    separated_pair(lines(first), line_ending, lines(second))
+}
+
+/// a row of one or more `item`s separated by runs of spaces or tabs, as when columns are padded
+/// out to line up. the row can be indented or have trailing spaces, but not its line ending.
+pub fn aligned_columns<'a, O, E, P>(item: P) -> impl Parser<&'a str, Vec<O>, E>
+where
+    E: ParserError<&'a str>,
+    P: Parser<&'a str, O, E>,
+{
+    delimited(space0, separated(1.., item, space1), space0)
+}
+
+/// the whole of `input`, or `None` if `parser` doesn't match it
+#[cfg(test)]
+fn parse_all<O>(
+    mut parser: impl Parser<&'static str, O, winnow::error::ErrMode<winnow::error::ContextError>>,
+    input: &'static str,
+) -> Option<O> {
+    parser.parse(input).ok()
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("1\n22\n333\n")]
+#[case::crlf("1\r\n22\r\n333\r\n")]
+#[case::no_trailing_newline("1\n22\n333")]
+fn test_lines(#[case] input: &'static str) {
+    let parsed: Option<Vec<u32>> = parse_all(lines(dec_uint), input);
+    assert_eq!(Some(vec![1, 22, 333]), parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("1\n22\n333\n")]
+#[case::crlf("1\r\n22\r\n333\r\n")]
+#[case::no_trailing_newline("1\n22\n333")]
+fn test_line_fold(#[case] input: &'static str) {
+    let parsed: Option<u32> = parse_all(
+        repeat(0.., line(dec_uint::<_, u32, _>)).fold(|| 0, |sum, n| sum + n),
+        input,
+    );
+    assert_eq!(Some(356), parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::empty("", Some(vec![]))]
+#[case::bad_line("1\nx\n3\n", None)]
+#[case::blank_line("1\n\n3\n", None)]
+fn test_lines_edge_cases(#[case] input: &'static str, #[case] expected: Option<Vec<u32>>) {
+    let parsed: Option<Vec<u32>> = parse_all(lines(dec_uint), input);
+    assert_eq!(expected, parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::range("3-5", Some(3..=5))]
+#[case::single("7-7", Some(7..=7))]
+#[case::no_upper_bound("3-", None)]
+#[case::spaces("3 - 5", None)]
+#[case::negative("-3-5", None)]
+#[case::overflow("3-18446744073709551616", None)]
+fn test_range_inclusive(
+    #[case] input: &'static str,
+    #[case] expected: Option<RangeInclusive<u64>>,
+) {
+    let parsed: Option<_> = parse_all(range_inclusive(), input);
+    assert_eq!(expected, parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("3-5\n10-14\n")]
+#[case::crlf("3-5\r\n10-14\r\n")]
+#[case::no_trailing_newline("3-5\r\n10-14")]
+fn test_range_inclusive_lines(#[case] input: &'static str) {
+    let parsed: Option<Vec<RangeInclusive<u64>>> = parse_all(lines(range_inclusive()), input);
+    assert_eq!(Some(vec![3..=5, 10..=14]), parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("#.\n.#\n", Some("#.\n.#"))]
+#[case::crlf("#.\r\n.#\r\n", Some("#.\n.#"))]
+#[case::no_trailing_newline("#.\n.#", Some("#.\n.#"))]
+#[case::empty("", None)]
+#[case::ragged("#.\n.\n", None)]
+#[case::unknown_cell("#.\n.?\n", None)]
+fn test_char_grid(#[case] input: &'static str, #[case] expected: Option<&str>) {
+    let parsed: Option<Grid<char>> =
+        parse_all(char_grid(|c| matches!(c, '#' | '.').then_some(c)), input);
+    assert_eq!(expected, parsed.map(|grid| grid.to_string()).as_deref());
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("ab\ncd\n")]
+#[case::crlf("ab\r\ncd\r\n")]
+#[case::no_trailing_newline("ab\r\ncd")]
+fn test_char_grid_any_char(#[case] input: &'static str) {
+    let parsed: Option<Grid<char>> = parse_all(char_grid(Some), input);
+    assert_eq!(
+        Some("ab\ncd"),
+        parsed.map(|grid| grid.to_string()).as_deref()
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_char_grid_ends_at_blank_line() {
+    let parsed: Option<(Grid<char>, Vec<u32>)> = parse_all(
+        separated_pair(
+            char_grid(|c| matches!(c, '#' | '.').then_some(c)),
+            line_ending,
+            lines(dec_uint),
+        ),
+        "#.\n.#\n\n1\n2\n",
+    );
+    let (grid, nums) = parsed.unwrap();
+    assert_eq!((2, 2), grid.size());
+    assert_eq!(vec![1, 2], nums);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("3-5\n10-14\n\n1\n5\n", Some((vec![3..=5, 10..=14], vec![1, 5])))]
+#[case::crlf("3-5\r\n10-14\r\n\r\n1\r\n5\r\n", Some((vec![3..=5, 10..=14], vec![1, 5])))]
+#[case::no_trailing_newline("3-5\n\n1\n5", Some((vec![3..=5], vec![1, 5])))]
+#[case::empty_second_section("3-5\n\n", Some((vec![3..=5], vec![])))]
+#[case::no_blank_line("3-5\n1\n", None)]
+#[case::two_blank_lines("3-5\n\n\n1\n", None)]
+fn test_blank_line_sections(
+    #[case] input: &'static str,
+    #[case] expected: Option<(Vec<RangeInclusive<u64>>, Vec<u64>)>,
+) {
+    let parsed: Option<_> = parse_all(blank_line_sections(range_inclusive(), dec_uint), input);
+    assert_eq!(expected, parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::lf("123 328  51\n 45 64  387\n*   +   *  \n")]
+#[case::crlf("123 328  51\r\n 45 64  387\r\n*   +   *  \r\n")]
+#[case::no_trailing_newline("123 328  51\n 45 64  387\n*   +   *  ")]
+#[case::tabs("123\t328\t51\n45\t64\t387\n*\t+\t*\n")]
+fn test_aligned_columns(#[case] input: &'static str) {
+    let field = take_till(1.., [' ', '\t', '\r', '\n']);
+    let parsed: Option<_> = parse_all(lines(aligned_columns(field)), input);
+    assert_eq!(
+        Some(vec![
+            vec!["123", "328", "51"],
+            vec!["45", "64", "387"],
+            vec!["*", "+", "*"]
+        ]),
+        parsed
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_aligned_columns_ragged() {
+    let parsed: Option<Vec<Vec<u32>>> = parse_all(lines(aligned_columns(dec_uint)), "1 2 3\n4\n");
+    assert_eq!(Some(vec![vec![1, 2, 3], vec![4]]), parsed);
+}
+
+#[cfg(test)]
+#[rstest]
+#[case::numbers(" 12  3 45 ", Some(vec![12, 3, 45]))]
+#[case::one("7", Some(vec![7]))]
+#[case::empty("", None)]
+#[case::not_a_number("1 x 3", None)]
+fn test_aligned_columns_items(#[case] input: &'static str, #[case] expected: Option<Vec<u32>>) {
+    let parsed: Option<Vec<u32>> = parse_all(aligned_columns(dec_uint), input);
+    assert_eq!(expected, parsed);
+}